@@ -0,0 +1,87 @@
+use std::fmt;
+use std::str::FromStr;
+
+// How the tree counts for all of the slopes are combined into one answer.
+// The puzzle wants the product.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Aggregate {
+    Product,
+    Sum,
+    Min,
+    Max,
+}
+
+impl Aggregate {
+    // None if the answer is too big for a usize, which a product of enough
+    // slopes can easily be.
+    pub fn apply(&self, counts: &[usize]) -> Option<usize> {
+        match self {
+            Aggregate::Product => counts
+                .iter()
+                .try_fold(1usize, |product, &count| product.checked_mul(count)),
+            Aggregate::Sum => counts
+                .iter()
+                .try_fold(0usize, |sum, &count| sum.checked_add(count)),
+            Aggregate::Min => Some(counts.iter().copied().min().unwrap_or(0)),
+            Aggregate::Max => Some(counts.iter().copied().max().unwrap_or(0)),
+        }
+    }
+}
+
+impl FromStr for Aggregate {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Aggregate, String> {
+        match s {
+            "product" => Ok(Aggregate::Product),
+            "sum" => Ok(Aggregate::Sum),
+            "min" => Ok(Aggregate::Min),
+            "max" => Ok(Aggregate::Max),
+            _ => Err(format!(
+                "unknown aggregate {} (expected product, sum, min or max)",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Aggregate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Aggregate::Product => "product",
+            Aggregate::Sum => "sum",
+            Aggregate::Min => "minimum",
+            Aggregate::Max => "maximum",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_apply() {
+        let counts = [2, 7, 3, 4, 2];
+        assert_eq!(Aggregate::Product.apply(&counts), Some(336));
+        assert_eq!(Aggregate::Sum.apply(&counts), Some(18));
+        assert_eq!(Aggregate::Min.apply(&counts), Some(2));
+        assert_eq!(Aggregate::Max.apply(&counts), Some(7));
+        assert_eq!("sum".parse(), Ok(Aggregate::Sum));
+        assert!("mean".parse::<Aggregate>().is_err());
+    }
+
+    #[test]
+    fn test_overflow() {
+        let counts = [100_000; 4];
+        assert_eq!(Aggregate::Product.apply(&counts), None);
+        assert_eq!(
+            Aggregate::Product.apply(&counts[..3]),
+            Some(10usize.pow(15))
+        );
+        assert_eq!(Aggregate::Product.apply(&[usize::MAX, 0]), Some(0));
+        assert_eq!(Aggregate::Sum.apply(&[usize::MAX, 1]), None);
+        assert_eq!(Aggregate::Max.apply(&counts), Some(100_000));
+    }
+}
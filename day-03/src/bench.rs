@@ -0,0 +1,80 @@
+use std::mem;
+use std::time::Instant;
+
+use crate::bitmap::BitMap;
+use crate::grid::{Cell, Grid};
+use crate::map::Map;
+use crate::slope::PUZZLE_SLOPES;
+
+const WIDTH: usize = 31;
+
+// Generate a forest with the given number of rows, about one square in six a
+// tree, from a fixed seed so that runs can be compared with each other.
+fn generate(rows: usize) -> String {
+    let mut state: u64 = 0x2020_1203;
+    let mut input = String::with_capacity(rows * (WIDTH + 1));
+
+    for _ in 0..rows {
+        for _ in 0..WIDTH {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            input.push(if state.is_multiple_of(6) { '#' } else { '.' });
+        }
+        input.push('\n');
+    }
+
+    input
+}
+
+// Time counting the trees for the puzzle's slopes on a generated forest with
+// the grid backed Map and with the BitMap, and check that they agree.
+pub fn run(rows: usize) {
+    let slopes = PUZZLE_SLOPES;
+
+    println!("Generating a forest {} wide and {} long", WIDTH, rows);
+    let input = generate(rows);
+
+    let start = Instant::now();
+    let map = Map::new(Grid::parse(&input).expect("generated map is valid"));
+    let map_parse = start.elapsed();
+
+    let start = Instant::now();
+    let map_counts: Vec<usize> = slopes.iter().map(|slope| map.trees(slope)).collect();
+    let map_trees = start.elapsed();
+
+    let start = Instant::now();
+    let bitmap = BitMap::read(input.as_bytes()).expect("generated map is valid");
+    let bitmap_parse = start.elapsed();
+
+    let start = Instant::now();
+    let bitmap_counts = bitmap.trees_many(&slopes);
+    let bitmap_trees = start.elapsed();
+
+    let start = Instant::now();
+    let single_counts: Vec<usize> = slopes.iter().map(|slope| bitmap.trees(slope)).collect();
+    let single_trees = start.elapsed();
+
+    assert_eq!(map_counts, bitmap_counts, "the maps disagree");
+    assert_eq!(single_counts, bitmap_counts, "the bitmap counts disagree");
+
+    println!();
+    println!(
+        "Map:    {:>12} bytes, parsed in {:?}, counted trees in {:?}",
+        WIDTH * rows * mem::size_of::<Cell>(),
+        map_parse,
+        map_trees
+    );
+    println!(
+        "BitMap: {:>12} bytes, parsed in {:?}, counted trees in {:?}",
+        bitmap.bytes(),
+        bitmap_parse,
+        bitmap_trees
+    );
+    println!(
+        "BitMap counting one slope at a time took {:?}",
+        single_trees
+    );
+    println!();
+    println!("Both found these tree counts: {:?}", bitmap_counts);
+}
@@ -0,0 +1,218 @@
+use std::io::BufRead;
+
+use crate::grid::GridError;
+use crate::slope::Slope;
+
+// A map of trees packed one bit per square, 64 squares to a word, with every
+// row starting on a new word. It only knows about trees and always repeats
+// to the right like the puzzle's map, but it takes a 32nd of the memory of a
+// Vec<Vec<char>> and can be read straight from a file without holding the
+// text in memory. The --bitmap option counts the trees with it.
+pub struct BitMap {
+    words: Vec<u64>,
+    words_per_row: usize,
+    width: usize,
+    height: usize,
+}
+
+impl BitMap {
+    pub fn read<R: BufRead>(reader: R) -> Result<BitMap, GridError> {
+        let mut bitmap = BitMap {
+            words: vec![],
+            words_per_row: 0,
+            width: 0,
+            height: 0,
+        };
+
+        // Blank lines are only allowed at the end of the file.
+        let mut blank_row = None;
+
+        for (y, line) in reader.lines().enumerate() {
+            let line = line.map_err(|error| GridError::Unreadable {
+                row: y + 1,
+                reason: error.to_string(),
+            })?;
+            let line = line.trim_end_matches('\r');
+
+            if line.is_empty() {
+                blank_row.get_or_insert(y + 1);
+                continue;
+            }
+
+            if let Some(row) = blank_row {
+                return Err(GridError::RaggedRow {
+                    row,
+                    expected: bitmap.width,
+                    found: 0,
+                });
+            }
+
+            if y == 0 {
+                bitmap.width = line.chars().count();
+                bitmap.words_per_row = bitmap.width.div_ceil(64);
+            }
+
+            let row_start = bitmap.words.len();
+            bitmap.words.resize(row_start + bitmap.words_per_row, 0);
+
+            let mut found = 0;
+            for (x, c) in line.chars().enumerate() {
+                match c {
+                    '.' => {}
+                    '#' => {
+                        if x < bitmap.width {
+                            bitmap.words[row_start + x / 64] |= 1 << (x % 64);
+                        }
+                    }
+                    _ => {
+                        return Err(GridError::InvalidCell {
+                            row: y + 1,
+                            column: x + 1,
+                            found: c,
+                        })
+                    }
+                }
+                found += 1;
+            }
+
+            if found != bitmap.width {
+                return Err(GridError::RaggedRow {
+                    row: y + 1,
+                    expected: bitmap.width,
+                    found,
+                });
+            }
+
+            bitmap.height += 1;
+        }
+
+        if bitmap.height == 0 {
+            return Err(GridError::Empty);
+        }
+
+        Ok(bitmap)
+    }
+
+    pub fn bytes(&self) -> usize {
+        self.words.len() * 8
+    }
+
+    pub fn trees(&self, slope: &Slope) -> usize {
+        self.trees_many(std::slice::from_ref(slope))[0]
+    }
+
+    // Count the trees for several slopes in a single pass down the map, so
+    // each row is only loaded once no matter how many slopes there are.
+    pub fn trees_many(&self, slopes: &[Slope]) -> Vec<usize> {
+        let mut columns = vec![0; slopes.len()];
+        let mut counts = vec![0; slopes.len()];
+        let steps: Vec<usize> = slopes
+            .iter()
+            .map(|slope| slope.right % self.width)
+            .collect();
+
+        for y in 0..self.height {
+            let row = &self.words[y * self.words_per_row..(y + 1) * self.words_per_row];
+
+            for (i, slope) in slopes.iter().enumerate() {
+                if y % slope.down != 0 {
+                    continue;
+                }
+
+                let x = columns[i];
+                counts[i] += (row[x / 64] >> (x % 64) & 1) as usize;
+
+                columns[i] += steps[i];
+                if columns[i] >= self.width {
+                    columns[i] -= self.width;
+                }
+            }
+        }
+
+        counts
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grid::Grid;
+    use crate::map::Map;
+
+    const TEST_MAP: &str = "..##.......\n#...#...#..\n.#....#..#.\n..#.#...#.#\n.#...##..#.\n..#.##.....\n.#.#.#....#\n.#........#\n#.##...#...\n#...##....#\n.#..#...#.#\n";
+
+    fn slopes() -> Vec<Slope> {
+        vec![
+            Slope { right: 1, down: 1 },
+            Slope { right: 3, down: 1 },
+            Slope { right: 5, down: 1 },
+            Slope { right: 7, down: 1 },
+            Slope { right: 1, down: 2 },
+            Slope { right: 0, down: 3 },
+            Slope { right: 12, down: 1 },
+        ]
+    }
+
+    #[test]
+    fn test_matches_map() {
+        let map = Map::new(Grid::parse(TEST_MAP).unwrap());
+        let bitmap = BitMap::read(TEST_MAP.as_bytes()).unwrap();
+        let expected: Vec<usize> = slopes().iter().map(|slope| map.trees(slope)).collect();
+
+        assert_eq!(expected[..5], [2, 7, 3, 4, 2]);
+        assert_eq!(bitmap.trees_many(&slopes()), expected);
+        assert_eq!(bitmap.trees(&Slope { right: 3, down: 1 }), 7);
+    }
+
+    #[test]
+    fn test_wide_rows() {
+        let row: String = (0..100)
+            .map(|x| if x % 3 == 0 { '#' } else { '.' })
+            .collect();
+        let input = format!("{}\n{}\n{}\n", row, row, row);
+        let map = Map::new(Grid::parse(&input).unwrap());
+        let bitmap = BitMap::read(input.as_bytes()).unwrap();
+
+        assert_eq!(bitmap.bytes(), 3 * 2 * 8);
+        for right in 0..120 {
+            let slope = Slope { right, down: 1 };
+            assert_eq!(bitmap.trees(&slope), map.trees(&slope));
+        }
+    }
+
+    #[test]
+    fn test_read_errors() {
+        assert_eq!(
+            BitMap::read("..#\n.O.\n".as_bytes()).err(),
+            Some(GridError::InvalidCell {
+                row: 2,
+                column: 2,
+                found: 'O'
+            })
+        );
+        assert_eq!(
+            BitMap::read("..#\n..##\n".as_bytes()).err(),
+            Some(GridError::RaggedRow {
+                row: 2,
+                expected: 3,
+                found: 4
+            })
+        );
+        assert_eq!(
+            BitMap::read("..#\n\n..#\n".as_bytes()).err(),
+            Some(GridError::RaggedRow {
+                row: 2,
+                expected: 3,
+                found: 0
+            })
+        );
+        assert_eq!(BitMap::read("".as_bytes()).err(), Some(GridError::Empty));
+        assert_eq!(
+            BitMap::read(&b"..#\n.\xff.\n"[..])
+                .err()
+                .map(|error| error.to_string()),
+            Some("row 2: could not be read: stream did not contain valid UTF-8".to_string())
+        );
+        assert_eq!(BitMap::read("..#\n\n".as_bytes()).unwrap().bytes(), 8);
+    }
+}
@@ -0,0 +1,215 @@
+use std::convert::TryFrom;
+use std::fmt;
+use std::ops::Index;
+
+// Anything other than open ground and trees only shows up on maps read with a
+// terrain legend, and is kept as the character the legend knows it by.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Cell {
+    Open,
+    Tree,
+    Other(char),
+}
+
+impl Cell {
+    pub fn symbol(&self) -> char {
+        match self {
+            Cell::Open => '.',
+            Cell::Tree => '#',
+            Cell::Other(c) => *c,
+        }
+    }
+}
+
+impl TryFrom<char> for Cell {
+    type Error = char;
+
+    fn try_from(c: char) -> Result<Cell, char> {
+        match c {
+            '.' => Ok(Cell::Open),
+            '#' => Ok(Cell::Tree),
+            _ => Err(c),
+        }
+    }
+}
+
+// Rows and columns in errors are 1-based so they line up with what an editor
+// shows for the input file.
+#[derive(Debug, PartialEq)]
+pub enum GridError {
+    Empty,
+    InvalidCell {
+        row: usize,
+        column: usize,
+        found: char,
+    },
+    RaggedRow {
+        row: usize,
+        expected: usize,
+        found: usize,
+    },
+    // Only maps read from a file can fail this way, such as when a line isn't
+    // valid UTF-8.
+    Unreadable {
+        row: usize,
+        reason: String,
+    },
+}
+
+impl fmt::Display for GridError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GridError::Empty => write!(f, "the grid has no rows"),
+            GridError::InvalidCell { row, column, found } => {
+                write!(f, "row {}, column {}: unexpected {:?}", row, column, found)
+            }
+            GridError::RaggedRow {
+                row,
+                expected,
+                found,
+            } => write!(
+                f,
+                "row {}: expected {} columns but found {}",
+                row, expected, found
+            ),
+            GridError::Unreadable { row, reason } => {
+                write!(f, "row {}: could not be read: {}", row, reason)
+            }
+        }
+    }
+}
+
+// The text of a map read from a file, or which row isn't valid UTF-8, in the
+// same words as when the BitMap reads it line by line.
+pub fn decode(input: Vec<u8>) -> Result<String, GridError> {
+    String::from_utf8(input).map_err(|error| {
+        let valid = &error.as_bytes()[..error.utf8_error().valid_up_to()];
+        GridError::Unreadable {
+            row: valid.iter().filter(|&&byte| byte == b'\n').count() + 1,
+            reason: "stream did not contain valid UTF-8".to_string(),
+        }
+    })
+}
+
+// A rectangular grid stored row by row in one flat vector.
+pub struct Grid<T> {
+    cells: Vec<T>,
+    width: usize,
+    height: usize,
+}
+
+impl<T: TryFrom<char>> Grid<T> {
+    pub fn parse(input: &str) -> Result<Grid<T>, GridError> {
+        Grid::parse_with(input, |c| T::try_from(c).ok())
+    }
+}
+
+impl<T> Grid<T> {
+    // Every row must be the same width and every character must parse into a
+    // cell. Trailing blank lines are ignored, as are \r line endings.
+    pub fn parse_with<F>(input: &str, parse_cell: F) -> Result<Grid<T>, GridError>
+    where
+        F: Fn(char) -> Option<T>,
+    {
+        let lines: Vec<&str> = input
+            .trim_end_matches(['\r', '\n'])
+            .lines()
+            .map(|line| line.trim_end_matches('\r'))
+            .collect();
+
+        let width = match lines.first() {
+            Some(line) if !line.is_empty() => line.chars().count(),
+            _ => return Err(GridError::Empty),
+        };
+
+        let mut cells = Vec::with_capacity(width * lines.len());
+
+        for (y, line) in lines.iter().enumerate() {
+            let mut found = 0;
+            for (x, c) in line.chars().enumerate() {
+                let cell = parse_cell(c).ok_or(GridError::InvalidCell {
+                    row: y + 1,
+                    column: x + 1,
+                    found: c,
+                })?;
+                cells.push(cell);
+                found += 1;
+            }
+
+            if found != width {
+                return Err(GridError::RaggedRow {
+                    row: y + 1,
+                    expected: width,
+                    found,
+                });
+            }
+        }
+
+        Ok(Grid {
+            cells,
+            width,
+            height: lines.len(),
+        })
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+}
+
+impl<T> Index<(usize, usize)> for Grid<T> {
+    type Output = T;
+
+    fn index(&self, (x, y): (usize, usize)) -> &T {
+        assert!(x < self.width, "column {} is outside the grid", x);
+        &self.cells[y * self.width + x]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let grid: Grid<Cell> = Grid::parse("..#\r\n#..\r\n\r\n").unwrap();
+
+        assert_eq!(grid.width(), 3);
+        assert_eq!(grid.height(), 2);
+        assert_eq!(grid[(2, 0)], Cell::Tree);
+        assert_eq!(grid[(0, 1)], Cell::Tree);
+        assert_eq!(grid[(1, 1)], Cell::Open);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(Grid::<Cell>::parse("").err(), Some(GridError::Empty));
+        assert_eq!(
+            Grid::<Cell>::parse("..#\n.O.\n").err(),
+            Some(GridError::InvalidCell {
+                row: 2,
+                column: 2,
+                found: 'O'
+            })
+        );
+        assert_eq!(
+            Grid::<Cell>::parse("..#\n..\n").err(),
+            Some(GridError::RaggedRow {
+                row: 2,
+                expected: 3,
+                found: 2
+            })
+        );
+        assert_eq!(
+            decode(b"..#\n.\xff.\n".to_vec())
+                .err()
+                .map(|error| error.to_string()),
+            Some("row 2: could not be read: stream did not contain valid UTF-8".to_string())
+        );
+        assert_eq!(decode(b"..#\n".to_vec()), Ok("..#\n".to_string()));
+    }
+}
@@ -0,0 +1,151 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::grid::Cell;
+
+pub struct Terrain {
+    pub name: String,
+    pub cost: usize,
+    pub blocks: bool,
+}
+
+pub struct Legend {
+    terrains: HashMap<char, Terrain>,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct LegendError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for LegendError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+// The legend for the puzzle's own maps: open ground is free and every tree
+// costs one.
+impl Default for Legend {
+    fn default() -> Legend {
+        let mut terrains = HashMap::new();
+        terrains.insert('.', terrain("open", 0, false));
+        terrains.insert('#', terrain("tree", 1, false));
+        Legend { terrains }
+    }
+}
+
+fn terrain(name: &str, cost: usize, blocks: bool) -> Terrain {
+    Terrain {
+        name: name.to_string(),
+        cost,
+        blocks,
+    }
+}
+
+impl Legend {
+    // The legend file has one terrain per line, made up of the character used
+    // on the map, the name of the terrain, and either its cost or "blocks" if
+    // it can't be moved through:
+    //
+    // . open 0
+    // # tree 1
+    // ~ water 5
+    // ^ rock blocks
+    pub fn parse(input: &str) -> Result<Legend, LegendError> {
+        let mut terrains = HashMap::new();
+
+        for (i, line) in input.lines().enumerate() {
+            let error = |message: String| LegendError {
+                line: i + 1,
+                message,
+            };
+
+            let parts: Vec<&str> = line.split_whitespace().collect();
+            let (symbol, name, cost) = match parts[..] {
+                [] => continue,
+                [symbol, name, cost] => (symbol, name, cost),
+                _ => return Err(error("expected <symbol> <name> <cost|blocks>".to_string())),
+            };
+
+            let mut symbol_chars = symbol.chars();
+            let symbol = match (symbol_chars.next(), symbol_chars.next()) {
+                (Some(c), None) => c,
+                _ => return Err(error(format!("{} is not a single character", symbol))),
+            };
+
+            let terrain = if cost == "blocks" {
+                terrain(name, 0, true)
+            } else {
+                let cost = cost
+                    .parse::<usize>()
+                    .map_err(|_| error(format!("{} is not a cost or \"blocks\"", cost)))?;
+                terrain(name, cost, false)
+            };
+
+            if terrains.insert(symbol, terrain).is_some() {
+                return Err(error(format!("{:?} is defined more than once", symbol)));
+            }
+        }
+
+        Ok(Legend { terrains })
+    }
+
+    pub fn get(&self, symbol: char) -> Option<&Terrain> {
+        self.terrains.get(&symbol)
+    }
+
+    pub fn blocks_anywhere(&self) -> bool {
+        self.terrains.values().any(|terrain| terrain.blocks)
+    }
+
+    // How a character on the map is read with this legend. The puzzle's own
+    // . and # keep their meaning for tree counting.
+    pub fn cell(&self, c: char) -> Option<Cell> {
+        if !self.terrains.contains_key(&c) {
+            return None;
+        }
+
+        match c {
+            '.' => Some(Cell::Open),
+            '#' => Some(Cell::Tree),
+            _ => Some(Cell::Other(c)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let legend = Legend::parse("# tree 1\n\n~ water 5\n^ rock blocks\n").unwrap();
+
+        assert_eq!(legend.get('~').unwrap().name, "water");
+        assert_eq!(legend.get('~').unwrap().cost, 5);
+        assert!(legend.get('^').unwrap().blocks);
+        assert!(legend.get('.').is_none());
+        assert_eq!(legend.cell('#'), Some(Cell::Tree));
+        assert_eq!(legend.cell('~'), Some(Cell::Other('~')));
+        assert_eq!(legend.cell('.'), None);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(Legend::parse("# tree\n").err().unwrap().line, 1);
+        assert_eq!(
+            Legend::parse(". open 0\n## tree 1\n").err().unwrap().line,
+            2
+        );
+        assert_eq!(Legend::parse("~ water lots\n").err().unwrap().line, 1);
+        assert_eq!(
+            Legend::parse("~ water 5\n~ lake 2\n").err(),
+            Some(LegendError {
+                line: 2,
+                message: "'~' is defined more than once".to_string()
+            })
+        );
+    }
+}
@@ -23,48 +23,363 @@
 // right 7, down 1
 // right 1, down 2
 //
-// Usage: cargo run <input-file>
+// The map must be rectangular and only contain . and # characters. Anything
+// else is reported with the row and column where it was found.
+//
+// Choosing the slopes:
+//
+// The five slopes above are only the default. Slopes can be given with
+// --slope 3,1 (as many times as needed) or read from a file with one slope
+// per line. --start moves the starting square from the top left corner to the
+// given column and row (counting from 0), and --aggregate picks how the tree
+// counts are combined, if not by multiplying them.
+//
+// Visualizing the runs:
+//
+// With --ascii the map is printed repeated as far to the right as the runs go,
+// with O marking open squares we passed through and X marking trees we hit,
+// like the puzzle description does. --color does the same but colors each
+// slope's marks differently. --image writes the same picture to a PPM or PGM
+// file (picked by the file extension), with --scale pixels per map square.
+//
+// Searching for the best slope:
+//
+// With --search R,D every slope with right 0 to R and down 1 to D is checked
+// (in parallel) and all of the slopes tied for the fewest trees are listed.
+// Adding --pareto lists the slopes that trade off fewest trees against fewest
+// steps instead, i.e. the ones that no other slope beats on both counts.
+//
+// Map topology:
+//
+// By default the map repeats to the right forever, like the puzzle says. With
+// --topology it can instead repeat in every direction (torus), stick to its
+// edges (clamp), repeat mirrored (reflect) or not repeat at all (none), in
+// which case a run ends as soon as it leaves the map. A torus has no bottom,
+// so a run on one keeps going until it's back on its starting square.
+//
+// Terrain costs:
+//
+// With --legend the map is read using a legend file that lists the terrain
+// each character stands for and what it costs to land on, or that it blocks
+// the way entirely. A run stops before blocking terrain, so its trees, steps
+// and pictures end there too. Each slope then also gets the total cost of its
+// run broken down by terrain, and where it was blocked. Without a legend, . is free, # costs 1 and nothing else is
+// allowed on the map.
+//
+// Finding the best route:
+//
+// A slope isn't the only way down the hill. With --pathfind S,D we look for
+// the route from the top left corner to the bottom row that hits the fewest
+// trees, where every move goes down 1 to D rows and left or right up to S
+// columns, and compare it with the best of the slopes above.
+//
+// Exact lines:
+//
+// A slope run only lands on a square every whole step, so it can skip right
+// over trees that the sled would clip on the way. With --exact we also count
+// every tree on the straight line from the top left square along each slope,
+// and the pictures draw that line instead of the steps.
+//
+// Map statistics:
+//
+// --stats prints the tree density of each row and column, the columns with the
+// longest stretch without trees, and how often the map repeats across and
+// down. The tree counts always use the periods, so on a map that repeats
+// further down than it goes each slope only walks one cycle. It also points
+// out slopes that hit exactly the same trees. With --json the same
+// information is printed as JSON instead, and nothing else is. --json only
+// goes with --stats.
+//
+// Big forests:
+//
+// BitMap stores the trees one bit per square for maps with millions of rows.
+// With --bitmap the map is read straight from the file into one and only the
+// tree counts are printed, since that's all it can do; none of the options
+// that need the whole map can go with it.
+// --bench <rows> generates a forest that long and compares how long counting
+// the trees takes with it and with the regular Map.
+//
+// Usage: cargo run <input-file> [--ascii] [--color] [--image <file>] [--scale <pixels>]
+//                  [--search <right>,<down>] [--pareto]
+//                  [--topology wrap|torus|clamp|reflect|none]
+//                  [--legend <legend-file>] [--pathfind <side>,<down>]
+//                  [--exact] [--stats [--json]]
+//                  [--slope <right>,<down>]... [--slopes-file <file>]
+//                  [--start <column>,<row>] [--aggregate product|sum|min|max]
+//        cargo run --release -- <input-file> --bitmap [--slope <right>,<down>]...
+//                  [--slopes-file <file>] [--aggregate product|sum|min|max]
+//        cargo run --release -- --bench <rows>
 
-use std::{env, fs::File, io::BufRead, io::BufReader};
+use std::{env, fs, fs::File, io::BufReader};
 
 mod slope;
 use crate::slope::Slope;
 
+mod aggregate;
+
+mod grid;
+use crate::grid::{Cell, Grid, GridError};
+
+mod legend;
+use crate::legend::Legend;
+
 mod map;
 use crate::map::Map;
 
+mod options;
+use crate::options::Options;
+
+mod topology;
+
+mod render;
+use crate::render::ImageFormat;
+
+mod search;
+
+mod pathfind;
+
+mod bitmap;
+use crate::bitmap::BitMap;
+
+mod bench;
+
+mod stats;
+
 fn main() {
-    let args: Vec<String> = env::args().collect();
+    let args: Vec<String> = env::args().skip(1).collect();
 
-    if args.len() < 2 {
-        println!("Usage: cargo run <input-file>");
+    let options = match Options::parse(&args) {
+        Ok(options) => options,
+        Err(message) => {
+            println!("{}", message);
+            println!("{}", options::USAGE);
+            return;
+        }
+    };
+
+    if let Some(rows) = options.bench {
+        bench::run(rows);
         return;
     }
 
-    let input_file = &args[1];
+    let input_file = options
+        .input_file
+        .as_deref()
+        .expect("an input file is required without --bench");
 
-    let file = File::open(input_file).expect("no such file");
-    let buf = BufReader::new(file);
-    let lines: Vec<Vec<char>> = buf
-        .lines()
-        .map(|l| l.expect("could not parse line"))
-        .map(|s| s.chars().collect())
-        .collect();
+    let slopes = match read_slopes(&options) {
+        Ok(slopes) => slopes,
+        Err(message) => {
+            println!("{}", message);
+            return;
+        }
+    };
+
+    if options.bitmap {
+        let file = File::open(input_file).expect("no such file");
+        match BitMap::read(BufReader::new(file)) {
+            Ok(bitmap) => {
+                print_tree_counts(&slopes, &bitmap.trees_many(&slopes), &options);
+            }
+            Err(error) => println!("Could not read the map in {}: {}", input_file, error),
+        }
+        return;
+    }
+
+    let legend = match &options.legend_file {
+        None => Legend::default(),
+        Some(legend_file) => {
+            let legend_input = fs::read_to_string(legend_file).expect("no such legend file");
+            match Legend::parse(&legend_input) {
+                Ok(legend) => legend,
+                Err(error) => {
+                    println!("Could not read the legend in {}: {}", legend_file, error);
+                    return;
+                }
+            }
+        }
+    };
 
-    let map = Map::new(&lines);
+    let input = fs::read(input_file).expect("no such file");
+    let grid: Result<Grid<Cell>, GridError> =
+        grid::decode(input).and_then(|input| match options.legend_file {
+            None => Grid::parse(&input),
+            Some(_) => Grid::parse_with(&input, |c| legend.cell(c)),
+        });
+    let grid = match grid {
+        Ok(grid) => grid,
+        Err(error) => {
+            println!("Could not read the map in {}: {}", input_file, error);
+            return;
+        }
+    };
 
-    let slopes = vec![
-        Slope { right: 1, down: 1 },
-        Slope { right: 3, down: 1 },
-        Slope { right: 5, down: 1 },
-        Slope { right: 7, down: 1 },
-        Slope { right: 1, down: 2 },
-    ];
+    let map = Map::new(grid)
+        .with_topology(options.topology)
+        .with_legend(legend)
+        .with_start(options.start);
+
+    if options.stats && options.json {
+        print!("{}", stats::json(&map.stats(), &map, &slopes));
+        return;
+    }
 
     let tree_counts: Vec<usize> = slopes.iter().map(|slope| map.trees(slope)).collect();
+    let best = print_tree_counts(&slopes, &tree_counts, &options);
+    let min_tree_count = tree_counts[best];
+    let best_slope = &slopes[best];
+
+    if options.stats {
+        println!();
+        print!("{}", stats::text(&map.stats(), &map, &slopes));
+    }
+
+    if options.exact {
+        println!();
+        println!("Here are the tree counts along the exact line of each slope:");
+        println!();
+
+        for slope in &slopes {
+            println!(
+                "{}: {} trees in {} squares",
+                slope,
+                map.line_trees(slope),
+                map.line_path(slope).len()
+            );
+        }
+    }
+
+    if options.legend_file.is_some() {
+        println!();
+        println!("Here are the terrain costs for each slope:");
 
+        for slope in &slopes {
+            let report = map.cost(slope);
+
+            println!();
+            println!("{}: total cost {}", slope, report.total);
+            for (name, terrain_cost) in &report.terrains {
+                println!(
+                    "  {}: {} cells, cost {}",
+                    name, terrain_cost.cells, terrain_cost.cost
+                );
+            }
+            if let Some((x, y)) = report.blocked {
+                println!("  blocked at column {}, row {}", x + 1, y + 1);
+            }
+        }
+    }
+
+    if let Some((max_right, max_down)) = options.search {
+        let results = search::evaluate(&map, max_right, max_down);
+
+        println!();
+        if options.pareto {
+            println!(
+                "Slopes up to right {}, down {} with the best trade off of trees and steps:",
+                max_right, max_down
+            );
+            println!();
+            for result in search::pareto_front(&results) {
+                println!(
+                    "{}: {} trees in {} steps",
+                    result.slope, result.trees, result.steps
+                );
+            }
+        } else {
+            println!(
+                "Slopes up to right {}, down {} with the fewest trees:",
+                max_right, max_down
+            );
+            println!();
+            for result in search::fewest_trees(&results) {
+                println!("{}: {} trees", result.slope, result.trees);
+            }
+        }
+    }
+
+    if let Some((max_side, max_down)) = options.pathfind {
+        println!();
+        match pathfind::fewest_trees(&map, &pathfind::fan(max_side, max_down)) {
+            None => println!(
+                "There is no way to the bottom moving up to {} sideways and {} down",
+                max_side, max_down
+            ),
+            Some(route) => {
+                println!(
+                    "The best route moving up to {} sideways and {} down hits {} trees in {} steps, vs {} for {}",
+                    max_side,
+                    max_down,
+                    route.trees,
+                    route.positions.len() - 1,
+                    min_tree_count,
+                    best_slope
+                );
+                let positions: Vec<String> = route
+                    .positions
+                    .iter()
+                    .map(|(x, y)| format!("({}, {})", x, y))
+                    .collect();
+                println!("Route (column, row): {}", positions.join(" "));
+            }
+        }
+    }
+
+    let paths: Vec<Vec<(usize, usize)>> = slopes
+        .iter()
+        .map(|slope| {
+            if options.exact {
+                map.line_path(slope)
+            } else {
+                map.path(slope)
+            }
+        })
+        .collect();
+
+    if options.ascii || options.color {
+        println!();
+        print!("{}", render::ascii(&map, &paths, options.color));
+    }
+
+    if let Some(image_file) = &options.image_file {
+        let format = match ImageFormat::from_path(image_file) {
+            Some(format) => format,
+            None => {
+                println!("The image file must end in .ppm or .pgm");
+                return;
+            }
+        };
+        let image = render::image(&map, &paths, options.scale, format);
+        fs::write(image_file, image).expect("could not write image");
+        println!("Wrote the runs to {}", image_file);
+    }
+}
+
+// The slopes from --slopes-file followed by any given with --slope, or the
+// puzzle's slopes if there aren't any.
+fn read_slopes(options: &Options) -> Result<Vec<Slope>, String> {
+    let mut slopes: Vec<Slope> = vec![];
+    if let Some(slopes_file) = &options.slopes_file {
+        let slopes_input = fs::read_to_string(slopes_file).expect("no such slopes file");
+        let file_slopes = slope::parse_slopes(&slopes_input)
+            .map_err(|error| format!("Could not read the slopes in {}: {}", slopes_file, error))?;
+        slopes.extend(file_slopes);
+    }
+    slopes.extend(&options.slopes);
+    if slopes.is_empty() {
+        slopes.extend(&slope::PUZZLE_SLOPES);
+    }
+    Ok(slopes)
+}
+
+// Print each slope's tree count, the best slope and the aggregate of the
+// counts, returning which slope was best.
+fn print_tree_counts(slopes: &[Slope], tree_counts: &[usize], options: &Options) -> usize {
     println!();
-    println!("Here are the tree counts for each slope:");
+    println!(
+        "Here are the tree counts for each slope (topology: {}):",
+        options.topology
+    );
     println!();
 
     for (i, count) in tree_counts.iter().enumerate() {
@@ -78,14 +393,18 @@ fn main() {
         .iter()
         .position(|&tree_count| tree_count == *min_tree_count)
         .unwrap();
-    let best_slope = &slopes[min_tree_count_index];
 
     println!(
         "The slope with the fewest trees is {} with {} trees",
-        best_slope, min_tree_count
-    );
-    println!(
-        "The product of the tree counts is {}",
-        tree_counts.iter().product::<usize>()
+        slopes[min_tree_count_index], min_tree_count
     );
+    match options.aggregate.apply(tree_counts) {
+        Some(total) => println!("The {} of the tree counts is {}", options.aggregate, total),
+        None => println!(
+            "The {} of the tree counts is too large to work out",
+            options.aggregate
+        ),
+    }
+
+    min_tree_count_index
 }
@@ -1,35 +1,385 @@
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::iter;
+use std::sync::OnceLock;
+
+use crate::grid::{Cell, Grid};
+use crate::legend::Legend;
 use crate::slope::Slope;
+use crate::stats::{self, MapStats, Periods};
+use crate::topology::{self, Topology};
 
 pub struct Map {
-    map: Vec<Vec<char>>,
-    width: usize,
-    height: usize,
+    grid: Grid<Cell>,
+    topology: Topology,
+    legend: Legend,
+    start: (usize, usize),
+    // Worked out the first time a count needs them.
+    periods: OnceLock<Periods>,
+}
+
+#[derive(Default, Debug, PartialEq)]
+pub struct TerrainCost {
+    pub cells: usize,
+    pub cost: usize,
+}
+
+// What a run down one slope cost, broken down by terrain name. If the run ran
+// into terrain that blocks movement, it stops there and blocked has the
+// position it couldn't get into.
+#[derive(Default, Debug, PartialEq)]
+pub struct CostReport {
+    pub total: usize,
+    pub terrains: BTreeMap<String, TerrainCost>,
+    pub blocked: Option<(usize, usize)>,
 }
 
 impl Map {
-    pub fn new(lines: &Vec<Vec<char>>) -> Map {
-        return Map {
-            map: lines.clone(),
-            width: lines.iter().nth(0).unwrap().len(),
-            height: lines.len(),
+    pub fn new(grid: Grid<Cell>) -> Map {
+        Map {
+            grid,
+            topology: Topology::Wrap,
+            legend: Legend::default(),
+            start: (0, 0),
+            periods: OnceLock::new(),
+        }
+    }
+
+    // The legend has to know every symbol on the map, so it should be the one
+    // the map was parsed with.
+    pub fn with_legend(mut self, legend: Legend) -> Map {
+        self.legend = legend;
+        self
+    }
+
+    pub fn with_topology(mut self, topology: Topology) -> Map {
+        self.topology = topology;
+        self
+    }
+
+    // Runs start in the top left corner unless told otherwise. The start is
+    // given as (column, row).
+    pub fn with_start(mut self, start: (usize, usize)) -> Map {
+        self.start = start;
+        self
+    }
+
+    pub fn start(&self) -> (usize, usize) {
+        self.start
+    }
+
+    pub fn topology(&self) -> Topology {
+        self.topology
+    }
+
+    pub fn can_block(&self) -> bool {
+        self.legend.blocks_anywhere()
+    }
+
+    pub fn blocks(&self, cell: Cell) -> bool {
+        self.legend
+            .get(cell.symbol())
+            .is_some_and(|terrain| terrain.blocks)
+    }
+
+    pub fn width(&self) -> usize {
+        self.grid.width()
+    }
+
+    pub fn height(&self) -> usize {
+        self.grid.height()
+    }
+
+    // The cell at a position on the plane the map is laid out on, according
+    // to the map's topology, or None if the position is off the map.
+    pub fn cell_at(&self, x: isize, y: isize) -> Option<Cell> {
+        self.topology
+            .resolve(x, y, self.width(), self.height())
+            .map(|position| self.grid[position])
+    }
+
+    // Every run starts at the map's starting square and ends when it gets past
+    // the bottom row, or as soon as it leaves the map if the topology allows
+    // that. A torus has no bottom, so a run on one goes round until it's back
+    // where it started. A run also ends at the first square of terrain that
+    // blocks movement, without landing on it.
+    fn run<'a>(&'a self, slope: &Slope) -> impl Iterator<Item = ((usize, usize), Cell)> + 'a {
+        self.moves(slope)
+            .take_while(move |&(_, cell)| !self.blocks(cell))
+    }
+
+    // The squares a run would land on if nothing blocked it.
+    fn moves<'a>(&'a self, slope: &Slope) -> impl Iterator<Item = ((usize, usize), Cell)> + 'a {
+        let Slope { right, down } = *slope;
+        let (start_x, start_y) = self.start;
+        let steps = match self.topology {
+            Topology::Torus => topology::torus_cycle(right, down, self.width(), self.height()),
+            _ => usize::MAX,
         };
+
+        (0..steps)
+            .map(move |step| (start_x + step * right, start_y + step * down))
+            .take_while(move |&(_, y)| self.topology == Topology::Torus || y < self.height())
+            .map_while(move |position| {
+                let (x, y) = self.on_torus(position);
+                self.cell_at(x as isize, y as isize)
+                    .map(|cell| ((x, y), cell))
+            })
+    }
+
+    // Runs on a torus can go round it many times, so their positions are
+    // kept on the map itself rather than spread over copies of it.
+    fn on_torus(&self, (x, y): (usize, usize)) -> (usize, usize) {
+        match self.topology {
+            Topology::Torus => (x % self.width(), y % self.height()),
+            _ => (x, y),
+        }
     }
 
+    // The positions visited going down the given slope, with x left unwrapped
+    // so that the path can be drawn on the repeated map, except on a torus.
+    pub fn path(&self, slope: &Slope) -> Vec<(usize, usize)> {
+        self.run(slope).map(|(position, _)| position).collect()
+    }
+
+    pub fn steps(&self, slope: &Slope) -> usize {
+        self.run(slope).count()
+    }
+
+    // On a map that repeats further down than it goes, only one cycle of the
+    // run is walked and the count is multiplied up from there. A run that
+    // could be blocked has to be walked to find out where it stops.
     pub fn trees(&self, slope: &Slope) -> usize {
-        let mut x = 0;
-        let mut y = 0;
+        if self.topology == Topology::Wrap && !self.can_block() {
+            let periods = self.periods();
+            if periods.vertical < self.height() {
+                return stats::trees(self, slope, periods);
+            }
+        }
+        self.walk_trees(slope)
+    }
+
+    // The trees on every square of the run, however long the map is.
+    pub fn walk_trees(&self, slope: &Slope) -> usize {
+        self.run(slope)
+            .filter(|&(_, cell)| cell == Cell::Tree)
+            .count()
+    }
+
+    // Every square that the straight line from the middle of the starting
+    // square passes through, rather than just the squares a whole number of
+    // steps along the slope. That makes the slope a ratio, so right 2, down 7
+    // passes through a square in every row instead of every 7th one. When the
+    // line goes exactly through a corner it skips straight to the diagonal
+    // square, since it only touches the other two at a point. On a torus the
+    // line goes round until it's back at the middle of the starting square.
+    fn line<'a>(&'a self, slope: &Slope) -> impl Iterator<Item = ((usize, usize), Cell)> + 'a {
+        let Slope { right, down } = *slope;
+
+        // The line is at (x + 0.5, y + 0.5) + t * (right, down), so comparing
+        // these tells us which edge of the square (x, y) it crosses first.
+        // The squares are counted from the start, which is added back on after.
+        let (start_x, start_y) = self.start;
+
+        // The line passes through the middle of a square every right / g
+        // across and down / g down, and the squares between those repeat.
+        let rows = match self.topology {
+            Topology::Torus => {
+                let g = topology::gcd(right, down);
+                let cycle = topology::torus_cycle(right / g, down / g, self.width(), self.height());
+                cycle * (down / g)
+            }
+            _ => usize::MAX,
+        };
+
+        iter::successors(Some((0, 0)), move |&(x, y)| {
+            let to_right_edge = (2 * x + 1) * down;
+            let to_bottom_edge = (2 * y + 1) * right;
+
+            Some(match to_right_edge.cmp(&to_bottom_edge) {
+                Ordering::Less => (x + 1, y),
+                Ordering::Greater => (x, y + 1),
+                Ordering::Equal => (x + 1, y + 1),
+            })
+        })
+        .take_while(move |&(_, y)| y < rows)
+        .map(move |(x, y)| (start_x + x, start_y + y))
+        .take_while(move |&(_, y)| self.topology == Topology::Torus || y < self.height())
+        .map_while(move |position| {
+            let (x, y) = self.on_torus(position);
+            self.cell_at(x as isize, y as isize)
+                .map(|cell| ((x, y), cell))
+        })
+        .take_while(move |&(_, cell)| !self.blocks(cell))
+    }
+
+    pub fn line_path(&self, slope: &Slope) -> Vec<(usize, usize)> {
+        self.line(slope).map(|(position, _)| position).collect()
+    }
+
+    pub fn line_trees(&self, slope: &Slope) -> usize {
+        self.line(slope)
+            .filter(|&(_, cell)| cell == Cell::Tree)
+            .count()
+    }
 
-        let mut tree_count = 0;
+    pub fn periods(&self) -> Periods {
+        *self.periods.get_or_init(|| stats::periods(self))
+    }
+
+    pub fn stats(&self) -> MapStats {
+        MapStats::new(self)
+    }
 
-        while y < self.height {
-            if self.map[y][x] == '#' {
-                tree_count += 1;
+    pub fn cost(&self, slope: &Slope) -> CostReport {
+        let mut report = CostReport::default();
+
+        for (position, cell) in self.moves(slope) {
+            let terrain = self
+                .legend
+                .get(cell.symbol())
+                .expect("the map has a symbol that isn't in its legend");
+
+            if terrain.blocks {
+                report.blocked = Some(position);
+                break;
             }
 
-            x = (x + slope.right) % self.width;
-            y = y + slope.down;
+            let terrain_cost = report.terrains.entry(terrain.name.clone()).or_default();
+            terrain_cost.cells += 1;
+            terrain_cost.cost += terrain.cost;
+            report.total += terrain.cost;
         }
 
-        return tree_count;
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_map(topology: Topology) -> Map {
+        Map::new(Grid::parse("..#\n#.#\n.##\n#..\n").unwrap()).with_topology(topology)
+    }
+
+    #[test]
+    fn test_trees_by_topology() {
+        let slope = Slope { right: 2, down: 1 };
+
+        assert_eq!(test_map(Topology::Wrap).trees(&slope), 3);
+        assert_eq!(test_map(Topology::Torus).trees(&slope), 6);
+        assert_eq!(test_map(Topology::Clamp).trees(&slope), 2);
+        assert_eq!(test_map(Topology::Reflect).trees(&slope), 3);
+        assert_eq!(test_map(Topology::NoWrap).trees(&slope), 1);
+        assert_eq!(test_map(Topology::NoWrap).steps(&slope), 2);
+    }
+
+    #[test]
+    fn test_torus_goes_round() {
+        let map = test_map(Topology::Torus);
+        let slope = Slope { right: 1, down: 1 };
+
+        // Every square of the 3 x 4 map on the diagonals, and nothing twice.
+        let path = map.path(&slope);
+        assert_eq!(path.len(), 12);
+        let mut squares = path.clone();
+        squares.sort();
+        squares.dedup();
+        assert_eq!(squares.len(), 12);
+        assert_eq!(map.trees(&slope), 6);
+        assert_eq!(test_map(Topology::Wrap).trees(&slope), 2);
+
+        // Going straight down only ever sees one column.
+        assert_eq!(map.path(&Slope { right: 0, down: 2 }), vec![(0, 0), (0, 2)]);
+
+        let line = map.line_path(&Slope { right: 1, down: 2 });
+        assert_eq!(line.len(), 18);
+        assert!(line.iter().all(|&(x, y)| x < 3 && y < 4));
+    }
+
+    #[test]
+    fn test_start() {
+        let map = test_map(Topology::Wrap).with_start((1, 1));
+        let slope = Slope { right: 1, down: 1 };
+
+        assert_eq!(map.path(&slope), vec![(1, 1), (2, 2), (3, 3)]);
+        assert_eq!(map.trees(&slope), 2);
+        assert_eq!(map.line_path(&slope), map.path(&slope));
+        assert_eq!(
+            map.line_path(&Slope { right: 1, down: 2 }),
+            vec![(1, 1), (1, 2), (2, 2), (2, 3)]
+        );
+    }
+
+    #[test]
+    fn test_line() {
+        let map = test_map(Topology::Wrap);
+
+        assert_eq!(
+            map.line_path(&Slope { right: 2, down: 7 }),
+            vec![(0, 0), (0, 1), (0, 2), (1, 2), (1, 3)]
+        );
+        assert_eq!(map.line_trees(&Slope { right: 2, down: 7 }), 2);
+        assert_eq!(map.trees(&Slope { right: 2, down: 7 }), 0);
+
+        assert_eq!(
+            map.line_path(&Slope { right: 1, down: 2 }),
+            vec![(0, 0), (0, 1), (1, 1), (1, 2), (1, 3), (2, 3)]
+        );
+        assert_eq!(map.line_trees(&Slope { right: 1, down: 2 }), 2);
+
+        let diagonal = Slope { right: 1, down: 1 };
+        assert_eq!(map.line_path(&diagonal), map.path(&diagonal));
+        assert_eq!(map.line_trees(&diagonal), map.trees(&diagonal));
+    }
+
+    #[test]
+    fn test_cost() {
+        let legend = Legend::parse(". open 0\n# tree 1\n~ water 5\n^ rock blocks\n").unwrap();
+        let grid = Grid::parse_with("..#\n#~#\n.#~\n^..\n", |c| legend.cell(c)).unwrap();
+        let map = Map::new(grid).with_legend(legend);
+
+        let report = map.cost(&Slope { right: 1, down: 1 });
+        assert_eq!(report.total, 10);
+        assert_eq!(report.terrains["water"], TerrainCost { cells: 2, cost: 10 });
+        assert_eq!(report.terrains["open"], TerrainCost { cells: 1, cost: 0 });
+        assert_eq!(report.blocked, Some((3, 3)));
+
+        let report = map.cost(&Slope { right: 0, down: 1 });
+        assert_eq!(report.total, 1);
+        assert_eq!(report.terrains["tree"], TerrainCost { cells: 1, cost: 1 });
+        assert_eq!(report.blocked, Some((0, 3)));
+
+        assert_eq!(map.trees(&Slope { right: 0, down: 1 }), 1);
+    }
+
+    #[test]
+    fn test_runs_stop_where_blocked() {
+        let legend = Legend::parse(". open 0\n# tree 1\n^ rock blocks\n").unwrap();
+        let grid = Grid::parse_with("...\n.^.\n..#\n...\n", |c| legend.cell(c)).unwrap();
+        let map = Map::new(grid).with_legend(legend);
+        let slope = Slope { right: 1, down: 1 };
+
+        // The tree behind the rock is never reached.
+        assert_eq!(map.cost(&slope).blocked, Some((1, 1)));
+        assert_eq!(map.path(&slope), vec![(0, 0)]);
+        assert_eq!(map.steps(&slope), 1);
+        assert_eq!(map.trees(&slope), 0);
+        assert_eq!(map.walk_trees(&slope), 0);
+        assert_eq!(map.line_path(&slope), vec![(0, 0)]);
+
+        // Jumping over it is fine.
+        let slope = Slope { right: 2, down: 2 };
+        assert_eq!(map.cost(&slope).blocked, None);
+        assert_eq!(map.trees(&slope), 1);
+    }
+
+    #[test]
+    fn test_default_legend_cost_matches_trees() {
+        let map = test_map(Topology::Wrap);
+        let slope = Slope { right: 1, down: 1 };
+
+        assert_eq!(map.cost(&slope).total, map.trees(&slope));
     }
 }
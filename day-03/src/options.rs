@@ -0,0 +1,187 @@
+use crate::aggregate::Aggregate;
+use crate::slope::Slope;
+use crate::topology::Topology;
+
+pub const USAGE: &str = concat!(
+    "Usage: cargo run <input-file> [--ascii] [--color]\n",
+    "                  [--image <file.ppm|file.pgm>] [--scale <pixels>]\n",
+    "                  [--search <right>,<down>] [--pareto]\n",
+    "                  [--topology wrap|torus|clamp|reflect|none]\n",
+    "                  [--legend <legend-file>] [--pathfind <side>,<down>]\n",
+    "                  [--exact] [--stats [--json]]\n",
+    "                  [--slope <right>,<down>]... [--slopes-file <file>]\n",
+    "                  [--start <column>,<row>] [--aggregate product|sum|min|max]\n",
+    "       cargo run --release -- <input-file> --bitmap [--slope <right>,<down>]...\n",
+    "                  [--slopes-file <file>] [--aggregate product|sum|min|max]\n",
+    "       cargo run --release -- --bench <rows>",
+);
+
+pub struct Options {
+    pub input_file: Option<String>,
+    pub ascii: bool,
+    pub color: bool,
+    pub image_file: Option<String>,
+    pub scale: usize,
+    pub search: Option<(usize, usize)>,
+    pub pareto: bool,
+    pub topology: Topology,
+    pub legend_file: Option<String>,
+    pub pathfind: Option<(usize, usize)>,
+    pub bench: Option<usize>,
+    pub exact: bool,
+    pub stats: bool,
+    pub json: bool,
+    pub slopes: Vec<Slope>,
+    pub slopes_file: Option<String>,
+    pub start: (usize, usize),
+    pub aggregate: Aggregate,
+    pub bitmap: bool,
+}
+
+impl Options {
+    pub fn parse(args: &[String]) -> Result<Options, String> {
+        let mut input_file = None;
+        let mut ascii = false;
+        let mut color = false;
+        let mut image_file = None;
+        let mut scale = 1;
+        let mut search = None;
+        let mut pareto = false;
+        let mut topology = Topology::Wrap;
+        let mut legend_file = None;
+        let mut pathfind = None;
+        let mut bench = None;
+        let mut exact = false;
+        let mut stats = false;
+        let mut json = false;
+        let mut slopes = vec![];
+        let mut slopes_file = None;
+        let mut start = (0, 0);
+        let mut aggregate = Aggregate::Product;
+        let mut bitmap = false;
+
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--ascii" => ascii = true,
+                "--color" => color = true,
+                "--image" => image_file = Some(value(&mut args, arg)?.to_string()),
+                "--scale" => {
+                    scale = value(&mut args, arg)?
+                        .parse::<usize>()
+                        .ok()
+                        .filter(|&scale| scale > 0)
+                        .ok_or("--scale must be a positive number")?;
+                }
+                "--search" => search = Some(bounds(value(&mut args, arg)?, arg)?),
+                "--pareto" => pareto = true,
+                "--topology" => topology = value(&mut args, arg)?.parse()?,
+                "--legend" => legend_file = Some(value(&mut args, arg)?.to_string()),
+                "--pathfind" => pathfind = Some(bounds(value(&mut args, arg)?, arg)?),
+                "--bench" => {
+                    bench = Some(
+                        value(&mut args, arg)?
+                            .parse::<usize>()
+                            .ok()
+                            .filter(|&rows| rows > 0)
+                            .ok_or("--bench needs a positive number of rows")?,
+                    );
+                }
+                "--exact" => exact = true,
+                "--stats" => stats = true,
+                "--json" => json = true,
+                "--slope" => slopes.push(value(&mut args, arg)?.parse()?),
+                "--slopes-file" => slopes_file = Some(value(&mut args, arg)?.to_string()),
+                "--start" => start = position(value(&mut args, arg)?, arg)?,
+                "--aggregate" => aggregate = value(&mut args, arg)?.parse()?,
+                "--bitmap" => bitmap = true,
+                flag if flag.starts_with("--") => return Err(format!("unknown option {}", flag)),
+                file => {
+                    if input_file.is_some() {
+                        return Err(format!("unexpected argument {}", file));
+                    }
+                    input_file = Some(file.to_string());
+                }
+            }
+        }
+
+        if input_file.is_none() && bench.is_none() {
+            return Err("missing input file".to_string());
+        }
+
+        if json && !stats {
+            return Err("--json only goes with --stats".to_string());
+        }
+
+        // The BitMap only knows where the trees are on a map that repeats to
+        // the right, and only counts them for runs from the top left corner.
+        let needs_grid = ascii
+            || color
+            || image_file.is_some()
+            || search.is_some()
+            || topology != Topology::Wrap
+            || legend_file.is_some()
+            || pathfind.is_some()
+            || exact
+            || stats
+            || start != (0, 0);
+        if bitmap && needs_grid {
+            return Err(concat!(
+                "--bitmap only counts trees, so it can't be used with --ascii, --color, ",
+                "--image, --search, --topology, --legend, --pathfind, --exact, --stats ",
+                "or --start"
+            )
+            .to_string());
+        }
+
+        Ok(Options {
+            input_file,
+            ascii,
+            color,
+            image_file,
+            scale,
+            search,
+            pareto,
+            topology,
+            legend_file,
+            pathfind,
+            bench,
+            exact,
+            stats,
+            json,
+            slopes,
+            slopes_file,
+            start,
+            aggregate,
+            bitmap,
+        })
+    }
+}
+
+fn value<'a>(args: &mut std::slice::Iter<'a, String>, flag: &str) -> Result<&'a str, String> {
+    args.next()
+        .map(|value| value.as_str())
+        .ok_or(format!("{} needs a value", flag))
+}
+
+fn bounds(value: &str, flag: &str) -> Result<(usize, usize), String> {
+    let error = || format!("{} expects two numbers like 3,1 but got {}", flag, value);
+    let (right, down) = value.split_once(',').ok_or_else(error)?;
+    let right = right.trim().parse::<usize>().map_err(|_| error())?;
+    let down = down.trim().parse::<usize>().map_err(|_| error())?;
+
+    if down == 0 {
+        return Err(format!("{} needs a down bound of at least 1", flag));
+    }
+
+    Ok((right, down))
+}
+
+fn position(value: &str, flag: &str) -> Result<(usize, usize), String> {
+    let error = || format!("{} expects <column>,<row> like 2,0 but got {}", flag, value);
+    let (column, row) = value.split_once(',').ok_or_else(error)?;
+    let column = column.trim().parse::<usize>().map_err(|_| error())?;
+    let row = row.trim().parse::<usize>().map_err(|_| error())?;
+
+    Ok((column, row))
+}
@@ -0,0 +1,187 @@
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+
+use crate::grid::Cell;
+use crate::map::Map;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Move {
+    pub right: isize,
+    pub down: usize,
+}
+
+// Every move going down between 1 and max_down rows while shifting at most
+// max_side columns either way. fan(1, 1) is down, down-left and down-right.
+pub fn fan(max_side: usize, max_down: usize) -> Vec<Move> {
+    let max_side = max_side as isize;
+    (1..=max_down)
+        .flat_map(|down| (-max_side..=max_side).map(move |right| Move { right, down }))
+        .collect()
+}
+
+// The fewest trees it took to reach a state, which column on the repeated map
+// that was in, and the state we came from.
+struct Visit {
+    trees: usize,
+    x: isize,
+    previous: Option<State>,
+}
+
+type State = (isize, usize);
+
+#[derive(Debug, PartialEq)]
+pub struct Route {
+    pub trees: usize,
+    pub positions: Vec<(isize, usize)>,
+}
+
+// Find the route from the map's starting square to the bottom row that lands on the
+// fewest trees, using Dijkstra's algorithm. Like a slope run, the starting
+// square counts. Positions that are off the map or on terrain that blocks the
+// way can't be landed on. Returns None if the moves can't reach the bottom row.
+pub fn fewest_trees(map: &Map, moves: &[Move]) -> Option<Route> {
+    let width = map.width();
+    let bottom = map.height() - 1;
+
+    let (start_x, start_y) = map.start();
+    let start_x = start_x as isize;
+    let start_cell = map.cell_at(start_x, start_y as isize)?;
+    if map.blocks(start_cell) {
+        return None;
+    }
+
+    // Squares that look the same to the topology are the same state, but we
+    // remember where on the repeated map we actually were to draw the route.
+    let state = |x: isize, y: usize| (map.topology().canonical_column(x, width), y);
+
+    let start = state(start_x, start_y);
+    let start_trees = (start_cell == Cell::Tree) as usize;
+    let mut best: HashMap<State, Visit> = HashMap::new();
+    best.insert(
+        start,
+        Visit {
+            trees: start_trees,
+            x: start_x,
+            previous: None,
+        },
+    );
+
+    let mut queue = BinaryHeap::new();
+    queue.push(Reverse((start_trees, start)));
+
+    while let Some(Reverse((trees, current))) = queue.pop() {
+        let x = best[&current].x;
+        if trees > best[&current].trees {
+            continue;
+        }
+
+        if current.1 == bottom {
+            return Some(route(&best, current, trees));
+        }
+
+        for step in moves {
+            let (next_x, next_y) = (x + step.right, current.1 + step.down);
+            if next_y > bottom {
+                continue;
+            }
+
+            let cell = match map.cell_at(next_x, next_y as isize) {
+                Some(cell) if !map.blocks(cell) => cell,
+                _ => continue,
+            };
+
+            let next = state(next_x, next_y);
+            let next_trees = trees + (cell == Cell::Tree) as usize;
+
+            if best.get(&next).is_none_or(|visit| next_trees < visit.trees) {
+                let visit = Visit {
+                    trees: next_trees,
+                    x: next_x,
+                    previous: Some(current),
+                };
+                best.insert(next, visit);
+                queue.push(Reverse((next_trees, next)));
+            }
+        }
+    }
+
+    None
+}
+
+fn route(best: &HashMap<State, Visit>, end: State, trees: usize) -> Route {
+    let mut positions = vec![];
+    let mut current = Some(end);
+
+    while let Some(state) = current {
+        let visit = &best[&state];
+        positions.push((visit.x, state.1));
+        current = visit.previous;
+    }
+
+    positions.reverse();
+    Route { trees, positions }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grid::Grid;
+    use crate::legend::Legend;
+    use crate::slope::Slope;
+
+    #[test]
+    fn test_fan() {
+        assert_eq!(
+            fan(1, 1),
+            vec![
+                Move { right: -1, down: 1 },
+                Move { right: 0, down: 1 },
+                Move { right: 1, down: 1 }
+            ]
+        );
+        assert_eq!(fan(2, 3).len(), 15);
+    }
+
+    #[test]
+    fn test_fewest_trees_weaves_between_trees() {
+        let map = Map::new(Grid::parse(".#..\n#.##\n.#.#\n#.#.\n").unwrap());
+        let route = fewest_trees(&map, &fan(1, 1)).unwrap();
+
+        assert_eq!(route.trees, 0);
+        assert_eq!(route.positions.len(), 4);
+        for (x, y) in route.positions {
+            assert_eq!(map.cell_at(x, y as isize), Some(Cell::Open));
+        }
+        assert_eq!(map.trees(&Slope { right: 3, down: 1 }), 1);
+    }
+
+    #[test]
+    fn test_fewest_trees_counts_unavoidable_trees() {
+        let map = Map::new(Grid::parse("...\n###\n...\n").unwrap());
+        let route = fewest_trees(&map, &fan(1, 1)).unwrap();
+        assert_eq!(route.trees, 1);
+
+        let route = fewest_trees(&map, &fan(0, 2)).unwrap();
+        assert_eq!(route.trees, 0);
+        assert_eq!(route.positions, vec![(0, 0), (0, 2)]);
+    }
+
+    #[test]
+    fn test_fewest_trees_from_start() {
+        let map = Map::new(Grid::parse("#..\n...\n.#.\n").unwrap()).with_start((1, 1));
+        let route = fewest_trees(&map, &fan(0, 1)).unwrap();
+
+        assert_eq!(route.trees, 1);
+        assert_eq!(route.positions, vec![(1, 1), (1, 2)]);
+    }
+
+    #[test]
+    fn test_fewest_trees_blocked() {
+        let legend = Legend::parse(". open 0\n^ rock blocks\n").unwrap();
+        let grid = Grid::parse_with("...\n^^^\n...\n", |c| legend.cell(c)).unwrap();
+        let map = Map::new(grid).with_legend(legend);
+
+        assert_eq!(fewest_trees(&map, &fan(1, 1)), None);
+        assert!(fewest_trees(&map, &fan(1, 2)).is_some());
+    }
+}
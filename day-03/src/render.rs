@@ -0,0 +1,223 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::grid::Cell;
+use crate::map::Map;
+
+#[derive(Clone, Copy)]
+struct Color {
+    r: u8,
+    g: u8,
+    b: u8,
+    ansi: u8,
+}
+
+const fn color(r: u8, g: u8, b: u8, ansi: u8) -> Color {
+    Color { r, g, b, ansi }
+}
+
+impl Color {
+    fn darker(&self) -> Color {
+        Color {
+            r: self.r / 2,
+            g: self.g / 2,
+            b: self.b / 2,
+            ansi: self.ansi,
+        }
+    }
+
+    fn gray(&self) -> u8 {
+        ((self.r as usize * 299 + self.g as usize * 587 + self.b as usize * 114) / 1000) as u8
+    }
+}
+
+// Each path gets the next color in the list, wrapping around if there are
+// more paths than colors.
+const PALETTE: [Color; 6] = [
+    color(220, 40, 40, 31),
+    color(40, 90, 230, 34),
+    color(240, 160, 20, 33),
+    color(200, 40, 200, 35),
+    color(20, 190, 200, 36),
+    color(120, 200, 40, 32),
+];
+
+const OPEN: Color = color(255, 255, 255, 0);
+const TREE: Color = color(34, 110, 34, 0);
+const OTHER: Color = color(150, 150, 150, 0);
+const OFF_MAP: Color = color(0, 0, 0, 0);
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum ImageFormat {
+    Ppm,
+    Pgm,
+}
+
+impl ImageFormat {
+    pub fn from_path(path: &str) -> Option<ImageFormat> {
+        match Path::new(path).extension()?.to_str()? {
+            "ppm" => Some(ImageFormat::Ppm),
+            "pgm" => Some(ImageFormat::Pgm),
+            _ => None,
+        }
+    }
+}
+
+// The part of the repeated map that the runs cover, along with which slope
+// visited each position. When runs cross, the later slope wins.
+struct Canvas<'a> {
+    map: &'a Map,
+    width: usize,
+    visits: HashMap<(usize, usize), usize>,
+}
+
+impl<'a> Canvas<'a> {
+    fn new(map: &'a Map, paths: &[Vec<(usize, usize)>]) -> Canvas<'a> {
+        let mut visits = HashMap::new();
+        let mut max_x = 0;
+
+        for (i, path) in paths.iter().enumerate() {
+            for &position in path {
+                max_x = max_x.max(position.0);
+                visits.insert(position, i);
+            }
+        }
+
+        let repeats = max_x / map.width() + 1;
+
+        Canvas {
+            map,
+            width: repeats * map.width(),
+            visits,
+        }
+    }
+
+    fn cell(&self, x: usize, y: usize) -> (char, Option<Color>) {
+        let cell = match self.map.cell_at(x as isize, y as isize) {
+            None => return (' ', None),
+            Some(cell) => cell,
+        };
+        match self.visits.get(&(x, y)) {
+            None => (cell.symbol(), None),
+            Some(&i) => {
+                let color = PALETTE[i % PALETTE.len()];
+                if cell == Cell::Tree {
+                    ('X', Some(color))
+                } else {
+                    ('O', Some(color))
+                }
+            }
+        }
+    }
+
+    fn pixel(&self, x: usize, y: usize) -> Color {
+        match self.cell(x, y) {
+            ('X', Some(color)) => color.darker(),
+            (_, Some(color)) => color,
+            ('#', None) => TREE,
+            ('.', None) => OPEN,
+            (' ', None) => OFF_MAP,
+            _ => OTHER,
+        }
+    }
+}
+
+// Draw the runs the way the puzzle does, with O for open squares we passed
+// through and X for trees we hit. With color on, each slope's marks are
+// wrapped in its ANSI color code.
+pub fn ascii(map: &Map, paths: &[Vec<(usize, usize)>], color: bool) -> String {
+    let canvas = Canvas::new(map, paths);
+    let mut output = String::new();
+
+    for y in 0..map.height() {
+        for x in 0..canvas.width {
+            match canvas.cell(x, y) {
+                (c, Some(slope_color)) if color => {
+                    let weight = if c == 'X' { "1;" } else { "" };
+                    output.push_str(&format!("\x1b[{}{}m{}\x1b[0m", weight, slope_color.ansi, c));
+                }
+                (c, _) => output.push(c),
+            }
+        }
+        output.push('\n');
+    }
+
+    output
+}
+
+// Draw the runs as a binary PPM (color) or PGM (grayscale) image, using a
+// square of scale x scale pixels per map cell.
+pub fn image(
+    map: &Map,
+    paths: &[Vec<(usize, usize)>],
+    scale: usize,
+    format: ImageFormat,
+) -> Vec<u8> {
+    let canvas = Canvas::new(map, paths);
+    let width = canvas.width * scale;
+    let height = map.height() * scale;
+
+    let magic = match format {
+        ImageFormat::Ppm => "P6",
+        ImageFormat::Pgm => "P5",
+    };
+    let mut output = format!("{}\n{} {}\n255\n", magic, width, height).into_bytes();
+
+    for y in 0..height {
+        for x in 0..width {
+            let color = canvas.pixel(x / scale, y / scale);
+            match format {
+                ImageFormat::Ppm => output.extend_from_slice(&[color.r, color.g, color.b]),
+                ImageFormat::Pgm => output.push(color.gray()),
+            }
+        }
+    }
+
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grid::Grid;
+    use crate::slope::Slope;
+
+    fn test_map() -> Map {
+        Map::new(
+            Grid::parse(
+                "..##
+#...
+.#..
+..#.
+",
+            )
+            .unwrap(),
+        )
+    }
+
+    #[test]
+    fn test_ascii() {
+        let map = test_map();
+        let paths = vec![map.path(&Slope { right: 2, down: 1 })];
+        assert_eq!(
+            ascii(&map, &paths, false),
+            "O.##..##\n#.O.#...\n.#..O#..\n..#...X.\n"
+        );
+    }
+
+    #[test]
+    fn test_image_header_and_size() {
+        let map = test_map();
+        let paths = vec![
+            map.path(&Slope { right: 1, down: 1 }),
+            map.path(&Slope { right: 1, down: 2 }),
+        ];
+        let ppm = image(&map, &paths, 2, ImageFormat::Ppm);
+        let pgm = image(&map, &paths, 2, ImageFormat::Pgm);
+
+        assert!(ppm.starts_with(b"P6\n8 8\n255\n"));
+        assert_eq!(ppm.len(), "P6\n8 8\n255\n".len() + 8 * 8 * 3);
+        assert!(pgm.starts_with(b"P5\n8 8\n255\n"));
+        assert_eq!(pgm.len(), "P5\n8 8\n255\n".len() + 8 * 8);
+    }
+}
@@ -0,0 +1,153 @@
+use std::thread;
+
+use crate::map::Map;
+use crate::slope::Slope;
+
+pub struct SlopeResult {
+    pub slope: Slope,
+    pub trees: usize,
+    pub steps: usize,
+}
+
+impl SlopeResult {
+    fn dominates(&self, other: &SlopeResult) -> bool {
+        self.trees <= other.trees
+            && self.steps <= other.steps
+            && (self.trees < other.trees || self.steps < other.steps)
+    }
+}
+
+// Count the trees for every slope with right from 0 to max_right and down from
+// 1 to max_down, splitting the slopes up between one thread per core.
+pub fn evaluate(map: &Map, max_right: usize, max_down: usize) -> Vec<SlopeResult> {
+    let slopes: Vec<Slope> = (1..=max_down)
+        .flat_map(|down| (0..=max_right).map(move |right| Slope { right, down }))
+        .collect();
+
+    let threads = thread::available_parallelism().map_or(1, |n| n.get());
+    let chunk_size = slopes.len().div_ceil(threads).max(1);
+
+    thread::scope(|scope| {
+        let handles: Vec<_> = slopes
+            .chunks(chunk_size)
+            .map(|chunk| {
+                scope.spawn(move || {
+                    chunk
+                        .iter()
+                        .map(|slope| SlopeResult {
+                            slope: *slope,
+                            trees: map.trees(slope),
+                            steps: map.steps(slope),
+                        })
+                        .collect::<Vec<SlopeResult>>()
+                })
+            })
+            .collect();
+
+        handles
+            .into_iter()
+            .flat_map(|handle| handle.join().expect("search thread panicked"))
+            .collect()
+    })
+}
+
+// All of the slopes that tie for the fewest trees.
+pub fn fewest_trees(results: &[SlopeResult]) -> Vec<&SlopeResult> {
+    let min_trees = match results.iter().map(|result| result.trees).min() {
+        Some(min_trees) => min_trees,
+        None => return vec![],
+    };
+
+    results
+        .iter()
+        .filter(|result| result.trees == min_trees)
+        .collect()
+}
+
+// The slopes that no other slope beats on both trees and steps, ordered from
+// fewest trees to fewest steps.
+pub fn pareto_front(results: &[SlopeResult]) -> Vec<&SlopeResult> {
+    let mut front: Vec<&SlopeResult> = results
+        .iter()
+        .filter(|result| !results.iter().any(|other| other.dominates(result)))
+        .collect();
+
+    front.sort_by_key(|result| (result.trees, result.steps));
+    front
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grid::Grid;
+
+    fn test_map() -> Map {
+        Map::new(
+            Grid::parse(
+                "..##.
+#...#
+.#...
+..#.#
+.#...
+",
+            )
+            .unwrap(),
+        )
+    }
+
+    #[test]
+    fn test_evaluate_matches_trees() {
+        let map = test_map();
+        let results = evaluate(&map, 4, 3);
+
+        assert_eq!(results.len(), 15);
+        for result in &results {
+            assert_eq!(result.trees, map.trees(&result.slope));
+        }
+    }
+
+    #[test]
+    fn test_fewest_trees_includes_ties() {
+        let map = test_map();
+        let results = evaluate(&map, 4, 3);
+        let best = fewest_trees(&results);
+
+        assert!(best.iter().all(|result| result.trees == 0));
+        assert!(best
+            .iter()
+            .any(|result| result.slope == Slope { right: 2, down: 1 }));
+        assert!(best
+            .iter()
+            .any(|result| result.slope == Slope { right: 0, down: 2 }));
+    }
+
+    #[test]
+    fn test_pareto_front() {
+        let results = vec![
+            SlopeResult {
+                slope: Slope { right: 1, down: 1 },
+                trees: 3,
+                steps: 10,
+            },
+            SlopeResult {
+                slope: Slope { right: 1, down: 2 },
+                trees: 1,
+                steps: 5,
+            },
+            SlopeResult {
+                slope: Slope { right: 1, down: 3 },
+                trees: 2,
+                steps: 4,
+            },
+        ];
+        let front: Vec<Slope> = pareto_front(&results)
+            .iter()
+            .map(|result| result.slope)
+            .collect();
+
+        assert_eq!(
+            front,
+            vec![Slope { right: 1, down: 2 }, Slope { right: 1, down: 3 }]
+        );
+    }
+}
@@ -1,12 +1,112 @@
 use std::fmt;
+use std::str::FromStr;
 
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
 pub struct Slope {
     pub right: usize,
     pub down: usize,
 }
 
+// The slopes from part 2 of the puzzle, used when no others are given.
+pub const PUZZLE_SLOPES: [Slope; 5] = [
+    Slope { right: 1, down: 1 },
+    Slope { right: 3, down: 1 },
+    Slope { right: 5, down: 1 },
+    Slope { right: 7, down: 1 },
+    Slope { right: 1, down: 2 },
+];
+
 impl fmt::Display for Slope {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "(right {}, down {})", self.right, self.down)
     }
 }
+
+// Slopes are written as <right>,<down>, like 3,1.
+impl FromStr for Slope {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Slope, String> {
+        let (right, down) = s.split_once(',').ok_or(format!(
+            "{:?} is not a slope, expected <right>,<down> like 3,1",
+            s
+        ))?;
+
+        let number = |part: &str, name: &str| {
+            part.trim().parse::<usize>().map_err(|_| {
+                format!(
+                    "{:?} is not a slope, {} must be a whole number of squares but got {:?}",
+                    s,
+                    name,
+                    part.trim()
+                )
+            })
+        };
+
+        let slope = Slope {
+            right: number(right, "right")?,
+            down: number(down, "down")?,
+        };
+
+        if slope.down == 0 {
+            return Err(format!(
+                "{:?} is not a slope, down must be at least 1 or we'd never reach the bottom",
+                s
+            ));
+        }
+
+        Ok(slope)
+    }
+}
+
+// A slope file has one slope per line. Blank lines and lines starting with //
+// are skipped.
+pub fn parse_slopes(input: &str) -> Result<Vec<Slope>, String> {
+    input
+        .lines()
+        .enumerate()
+        .map(|(i, line)| (i, line.trim()))
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with("//"))
+        .map(|(i, line)| {
+            line.parse::<Slope>()
+                .map_err(|error| format!("line {}: {}", i + 1, error))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_str() {
+        assert_eq!("3,1".parse(), Ok(Slope { right: 3, down: 1 }));
+        assert_eq!(" 0 , 2 ".parse(), Ok(Slope { right: 0, down: 2 }));
+        assert_eq!(
+            "3".parse::<Slope>(),
+            Err("\"3\" is not a slope, expected <right>,<down> like 3,1".to_string())
+        );
+        assert_eq!(
+            "-1,2".parse::<Slope>(),
+            Err(
+                "\"-1,2\" is not a slope, right must be a whole number of squares but got \"-1\""
+                    .to_string()
+            )
+        );
+        assert!("3,0".parse::<Slope>().unwrap_err().contains("at least 1"));
+    }
+
+    #[test]
+    fn test_parse_slopes() {
+        assert_eq!(
+            parse_slopes("// puzzle slopes\n3,1\n\n1,2\n"),
+            Ok(vec![
+                Slope { right: 3, down: 1 },
+                Slope { right: 1, down: 2 }
+            ])
+        );
+        assert!(parse_slopes("3,1\n3;1\n")
+            .unwrap_err()
+            .starts_with("line 2: "));
+    }
+}
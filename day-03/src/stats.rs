@@ -0,0 +1,411 @@
+use std::collections::BTreeSet;
+
+use crate::grid::Cell;
+use crate::map::Map;
+use crate::slope::Slope;
+use crate::topology::{gcd, Topology};
+
+#[derive(Debug, PartialEq)]
+pub struct MapStats {
+    pub width: usize,
+    pub height: usize,
+    pub trees: usize,
+    pub row_density: Vec<f64>,
+    pub column_density: Vec<f64>,
+    // The longest stretch of squares without a tree going straight down, and
+    // every column that has a stretch that long.
+    pub longest_clear_run: usize,
+    pub longest_clear_columns: Vec<usize>,
+    pub periods: Periods,
+}
+
+// The map looks the same shifted right by horizontal squares (which always
+// divides the width) and shifted down by vertical squares (as far as the map
+// goes). A period the size of the map means the map doesn't repeat inside it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Periods {
+    pub horizontal: usize,
+    pub vertical: usize,
+}
+
+impl MapStats {
+    pub fn new(map: &Map) -> MapStats {
+        let (width, height) = (map.width(), map.height());
+        let tree = |x: usize, y: usize| map.cell_at(x as isize, y as isize) == Some(Cell::Tree);
+
+        let row_trees: Vec<usize> = (0..height)
+            .map(|y| (0..width).filter(|&x| tree(x, y)).count())
+            .collect();
+        let column_trees: Vec<usize> = (0..width)
+            .map(|x| (0..height).filter(|&y| tree(x, y)).count())
+            .collect();
+
+        let clear_runs: Vec<usize> = (0..width)
+            .map(|x| {
+                let mut longest = 0;
+                let mut current = 0;
+                for y in 0..height {
+                    current = if tree(x, y) { 0 } else { current + 1 };
+                    longest = longest.max(current);
+                }
+                longest
+            })
+            .collect();
+        let longest_clear_run = clear_runs.iter().copied().max().unwrap_or(0);
+
+        MapStats {
+            width,
+            height,
+            trees: row_trees.iter().sum(),
+            row_density: row_trees
+                .iter()
+                .map(|&trees| trees as f64 / width as f64)
+                .collect(),
+            column_density: column_trees
+                .iter()
+                .map(|&trees| trees as f64 / height as f64)
+                .collect(),
+            longest_clear_run,
+            longest_clear_columns: (0..width)
+                .filter(|&x| clear_runs[x] == longest_clear_run)
+                .collect(),
+            periods: map.periods(),
+        }
+    }
+}
+
+// Rows are compared whole, and so are columns, so each period takes a linear
+// number of comparisons and finding both looks at each square a few times.
+pub fn periods(map: &Map) -> Periods {
+    let (width, height) = (map.width(), map.height());
+    let cell = |x: usize, y: usize| map.cell_at(x as isize, y as isize);
+
+    // The map only repeats across if a whole number of copies of the period
+    // make up each row.
+    let horizontal = shortest_period(width, |a, b| (0..height).all(|y| cell(a, y) == cell(b, y)));
+    let horizontal = if width.is_multiple_of(horizontal) {
+        horizontal
+    } else {
+        width
+    };
+
+    let vertical = shortest_period(height, |a, b| (0..width).all(|x| cell(x, a) == cell(x, b)));
+
+    Periods {
+        horizontal,
+        vertical,
+    }
+}
+
+// The smallest p such that item i is the same as item i + p all the way
+// along, which is the length less the longest border: the longest run at the
+// start that's also at the end. The borders of every prefix are built up with
+// the prefix function, which makes O(length) comparisons in all.
+fn shortest_period<F>(length: usize, same: F) -> usize
+where
+    F: Fn(usize, usize) -> bool,
+{
+    let mut borders = vec![0; length];
+
+    for i in 1..length {
+        let mut border = borders[i - 1];
+        while border > 0 && !same(i, border) {
+            border = borders[border - 1];
+        }
+        if same(i, border) {
+            border += 1;
+        }
+        borders[i] = border;
+    }
+
+    length - borders.last().copied().unwrap_or(0)
+}
+
+// The same answer as walking the whole run, but only walking the slope until its
+// position on the map starts repeating and multiplying up from there. Only
+// maps that just repeat to the right can do this, so other topologies fall
+// back on the full run, as do maps with terrain that could block it.
+pub fn trees(map: &Map, slope: &Slope, periods: Periods) -> usize {
+    if map.topology() != Topology::Wrap || map.can_block() {
+        return map.walk_trees(slope);
+    }
+
+    let Periods {
+        horizontal: p,
+        vertical: q,
+    } = periods;
+
+    let (start_x, start_y) = map.start();
+    let steps = map.height().saturating_sub(start_y).div_ceil(slope.down);
+    let column_cycle = p / gcd(p, slope.right % p);
+    let row_cycle = q / gcd(q, slope.down % q);
+    let cycle = column_cycle / gcd(column_cycle, row_cycle) * row_cycle;
+
+    let mut prefix = vec![0];
+    for k in 0..cycle.min(steps) {
+        let x = (start_x + k * (slope.right % p)) % p;
+        let y = (start_y + k * slope.down) % q;
+        let is_tree = map.cell_at(x as isize, y as isize) == Some(Cell::Tree);
+        prefix.push(prefix[k] + is_tree as usize);
+    }
+
+    if cycle >= steps {
+        return prefix[steps];
+    }
+
+    (steps / cycle) * prefix[cycle] + prefix[steps % cycle]
+}
+
+// Pairs of slopes (by index) that hit exactly the same trees, leaving out
+// slopes that don't hit any. Trees are told apart by where they are on the
+// map itself, according to its topology.
+pub fn same_trees(map: &Map, slopes: &[Slope]) -> Vec<(usize, usize)> {
+    let hits: Vec<BTreeSet<(usize, usize)>> = slopes
+        .iter()
+        .map(|slope| {
+            map.path(slope)
+                .into_iter()
+                .filter_map(|(x, y)| {
+                    map.topology()
+                        .resolve(x as isize, y as isize, map.width(), map.height())
+                })
+                .filter(|&(x, y)| map.cell_at(x as isize, y as isize) == Some(Cell::Tree))
+                .collect()
+        })
+        .collect();
+
+    let mut pairs = vec![];
+    for i in 0..slopes.len() {
+        for j in i + 1..slopes.len() {
+            if !hits[i].is_empty() && hits[i] == hits[j] {
+                pairs.push((i, j));
+            }
+        }
+    }
+
+    pairs
+}
+
+fn json_list<T: ToString>(values: &[T]) -> String {
+    let values: Vec<String> = values.iter().map(|value| value.to_string()).collect();
+    format!("[{}]", values.join(", "))
+}
+
+fn json_densities(densities: &[f64]) -> String {
+    let values: Vec<String> = densities
+        .iter()
+        .map(|density| format!("{:.4}", density))
+        .collect();
+    format!("[{}]", values.join(", "))
+}
+
+pub fn text(stats: &MapStats, map: &Map, slopes: &[Slope]) -> String {
+    let mut output = String::new();
+    let density = stats.trees as f64 / (stats.width * stats.height) as f64;
+
+    output.push_str(&format!(
+        "The map is {} wide and {} long with {} trees ({:.1}% of squares)\n",
+        stats.width,
+        stats.height,
+        stats.trees,
+        density * 100.0
+    ));
+
+    let (densest_row, _) = stats
+        .row_density
+        .iter()
+        .enumerate()
+        .fold(
+            (0, -1.0),
+            |best, (y, &d)| if d > best.1 { (y, d) } else { best },
+        );
+    let (sparsest_row, _) =
+        stats.row_density.iter().enumerate().fold(
+            (0, 2.0),
+            |best, (y, &d)| if d < best.1 { (y, d) } else { best },
+        );
+    output.push_str(&format!(
+        "The densest row is {} ({:.1}% trees) and the sparsest is {} ({:.1}% trees)\n",
+        densest_row + 1,
+        stats.row_density[densest_row] * 100.0,
+        sparsest_row + 1,
+        stats.row_density[sparsest_row] * 100.0
+    ));
+
+    output.push_str("Tree density by column:\n");
+    for (x, density) in stats.column_density.iter().enumerate() {
+        output.push_str(&format!("  {:>3}: {:.1}%\n", x + 1, density * 100.0));
+    }
+
+    let columns: Vec<usize> = stats.longest_clear_columns.iter().map(|x| x + 1).collect();
+    output.push_str(&format!(
+        "The longest clear run straight down is {} squares, in column(s) {}\n",
+        stats.longest_clear_run,
+        json_list(&columns)
+    ));
+    output.push_str(&format!(
+        "The map repeats every {} columns and every {} rows\n",
+        stats.periods.horizontal, stats.periods.vertical
+    ));
+
+    for (i, j) in same_trees(map, slopes) {
+        output.push_str(&format!(
+            "{} and {} hit the same trees\n",
+            slopes[i], slopes[j]
+        ));
+    }
+
+    output
+}
+
+pub fn json(stats: &MapStats, map: &Map, slopes: &[Slope]) -> String {
+    let slope_counts: Vec<String> = slopes
+        .iter()
+        .map(|slope| {
+            format!(
+                "{{\"right\": {}, \"down\": {}, \"trees\": {}}}",
+                slope.right,
+                slope.down,
+                map.trees(slope)
+            )
+        })
+        .collect();
+    let same: Vec<String> = same_trees(map, slopes)
+        .iter()
+        .map(|&(i, j)| format!("[{}, {}]", i, j))
+        .collect();
+
+    let fields = [
+        format!("\"width\": {}", stats.width),
+        format!("\"height\": {}", stats.height),
+        format!("\"trees\": {}", stats.trees),
+        format!("\"row_density\": {}", json_densities(&stats.row_density)),
+        format!(
+            "\"column_density\": {}",
+            json_densities(&stats.column_density)
+        ),
+        format!("\"longest_clear_run\": {}", stats.longest_clear_run),
+        format!(
+            "\"longest_clear_columns\": {}",
+            json_list(&stats.longest_clear_columns)
+        ),
+        format!("\"horizontal_period\": {}", stats.periods.horizontal),
+        format!("\"vertical_period\": {}", stats.periods.vertical),
+        format!("\"slopes\": [{}]", slope_counts.join(", ")),
+        format!("\"same_trees\": [{}]", same.join(", ")),
+    ];
+
+    format!("{{\n  {}\n}}\n", fields.join(",\n  "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grid::Grid;
+
+    fn test_map(input: &str) -> Map {
+        Map::new(Grid::parse(input).unwrap())
+    }
+
+    #[test]
+    fn test_stats() {
+        let stats = test_map("#..#..\n......\n#..#..\n......\n#..#..\n").stats();
+
+        assert_eq!(stats.trees, 6);
+        assert_eq!(
+            stats.row_density,
+            vec![2.0 / 6.0, 0.0, 2.0 / 6.0, 0.0, 2.0 / 6.0]
+        );
+        assert_eq!(stats.column_density[0], 3.0 / 5.0);
+        assert_eq!(stats.column_density[1], 0.0);
+        assert_eq!(stats.longest_clear_run, 5);
+        assert_eq!(stats.longest_clear_columns, vec![1, 2, 4, 5]);
+        assert_eq!(
+            stats.periods,
+            Periods {
+                horizontal: 3,
+                vertical: 2
+            }
+        );
+    }
+
+    #[test]
+    fn test_periods_without_repeats() {
+        let stats = test_map("#..\n.#.\n#..\n..#\n").stats();
+        assert_eq!(
+            stats.periods,
+            Periods {
+                horizontal: 3,
+                vertical: 4
+            }
+        );
+    }
+
+    #[test]
+    fn test_periodic_trees_match_full_run() {
+        let input = "#..#...#..#...\n.#...#..#...#.\n..#.#....#.#..\n".repeat(40);
+        let periods = test_map(&input).stats().periods;
+        assert_eq!(periods.horizontal, 7);
+        assert_eq!(periods.vertical, 3);
+
+        for start in [(0, 0), (3, 1), (20, 7)] {
+            let map = test_map(&input).with_start(start);
+            for right in 0..16 {
+                for down in 1..5 {
+                    let slope = Slope { right, down };
+                    assert_eq!(trees(&map, &slope, periods), map.walk_trees(&slope));
+                    assert_eq!(map.trees(&slope), map.walk_trees(&slope));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_periods_of_a_long_map() {
+        // Too long to find the periods by trying every shift.
+        let stats = test_map(&"#..#.\n.#...\n...#.\n".repeat(100_000)).stats();
+        assert_eq!(
+            stats.periods,
+            Periods {
+                horizontal: 5,
+                vertical: 3
+            }
+        );
+
+        // A row that repeats, but not a whole number of times.
+        let stats = test_map("#.#.#\n#.#.#\n").stats();
+        assert_eq!(stats.periods.horizontal, 5);
+        assert_eq!(stats.periods.vertical, 1);
+    }
+
+    #[test]
+    fn test_same_trees() {
+        let map = test_map("....\n.#..\n....\n.#..\n");
+        let slopes = [
+            Slope { right: 1, down: 1 },
+            Slope { right: 5, down: 1 },
+            Slope { right: 0, down: 1 },
+            Slope { right: 2, down: 1 },
+            Slope { right: 6, down: 1 },
+        ];
+
+        assert_eq!(same_trees(&map, &slopes), vec![(0, 1)]);
+
+        // Going 5 across lands on the square 1 across on a wrapping map, but
+        // not once the map is reflected or clamped at its edges.
+        let slopes = [
+            Slope { right: 1, down: 1 },
+            Slope { right: 5, down: 1 },
+            Slope { right: 3, down: 1 },
+            Slope { right: 7, down: 1 },
+        ];
+        let map = test_map("....\n.#.#\n");
+        assert_eq!(same_trees(&map, &slopes), vec![(0, 1), (2, 3)]);
+
+        let map = test_map("....\n.##.\n").with_topology(Topology::Reflect);
+        assert_eq!(same_trees(&map, &slopes), vec![]);
+
+        let map = test_map("....\n.#.#\n").with_topology(Topology::Clamp);
+        assert_eq!(same_trees(&map, &slopes), vec![(1, 2), (1, 3), (2, 3)]);
+    }
+}
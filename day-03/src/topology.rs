@@ -0,0 +1,142 @@
+use std::fmt;
+use std::str::FromStr;
+
+// How positions off the edge of the map are treated.
+//
+// Wrap:    the map repeats to the right (and left) forever, as in the puzzle
+// Torus:   the map repeats in every direction, and a run goes round it until
+//          it gets back to a square it has already been on
+// Clamp:   positions past an edge stick to the last row or column
+// Reflect: the map repeats in every direction, mirrored every other time
+// NoWrap:  there is nothing past the edges of the map
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Topology {
+    Wrap,
+    Torus,
+    Clamp,
+    Reflect,
+    NoWrap,
+}
+
+impl Topology {
+    // The cell on a width x height map that the position (x, y) lands on, or
+    // None if it falls off the map.
+    pub fn resolve(
+        &self,
+        x: isize,
+        y: isize,
+        width: usize,
+        height: usize,
+    ) -> Option<(usize, usize)> {
+        let (width, height) = (width as isize, height as isize);
+
+        let (x, y) = match self {
+            Topology::Wrap => (x.rem_euclid(width), y),
+            Topology::Torus => (x.rem_euclid(width), y.rem_euclid(height)),
+            Topology::Clamp => (x.clamp(0, width - 1), y.clamp(0, height - 1)),
+            Topology::Reflect => (reflect(x, width), reflect(y, height)),
+            Topology::NoWrap => (x, y),
+        };
+
+        if x < 0 || x >= width || y < 0 || y >= height {
+            return None;
+        }
+
+        Some((x as usize, y as usize))
+    }
+
+    // Columns that behave the same way from here on down are given the same
+    // number, so searches don't treat every repeat of the map as new ground.
+    pub fn canonical_column(&self, x: isize, width: usize) -> isize {
+        let width = width as isize;
+        match self {
+            Topology::Wrap | Topology::Torus => x.rem_euclid(width),
+            Topology::Reflect => x.rem_euclid(2 * width),
+            Topology::Clamp | Topology::NoWrap => x,
+        }
+    }
+}
+
+pub fn gcd(a: usize, b: usize) -> usize {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+// How many steps of right, down it takes to get back to the same square on a
+// width x height torus, which is when a run on one ends.
+pub fn torus_cycle(right: usize, down: usize, width: usize, height: usize) -> usize {
+    let columns = width / gcd(width, right % width);
+    let rows = height / gcd(height, down % height);
+    columns / gcd(columns, rows) * rows
+}
+
+fn reflect(position: isize, size: isize) -> isize {
+    let position = position.rem_euclid(2 * size);
+    if position < size {
+        position
+    } else {
+        2 * size - 1 - position
+    }
+}
+
+impl FromStr for Topology {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Topology, String> {
+        match s {
+            "wrap" => Ok(Topology::Wrap),
+            "torus" => Ok(Topology::Torus),
+            "clamp" => Ok(Topology::Clamp),
+            "reflect" => Ok(Topology::Reflect),
+            "none" => Ok(Topology::NoWrap),
+            _ => Err(format!(
+                "unknown topology {} (expected wrap, torus, clamp, reflect or none)",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Topology {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Topology::Wrap => "wrap",
+            Topology::Torus => "torus",
+            Topology::Clamp => "clamp",
+            Topology::Reflect => "reflect",
+            Topology::NoWrap => "none",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve() {
+        assert_eq!(Topology::Wrap.resolve(7, 1, 3, 2), Some((1, 1)));
+        assert_eq!(Topology::Wrap.resolve(-1, 1, 3, 2), Some((2, 1)));
+        assert_eq!(Topology::Wrap.resolve(0, 2, 3, 2), None);
+        assert_eq!(Topology::Torus.resolve(7, -1, 3, 2), Some((1, 1)));
+        assert_eq!(Topology::Clamp.resolve(7, 5, 3, 2), Some((2, 1)));
+        assert_eq!(Topology::Clamp.resolve(-4, 0, 3, 2), Some((0, 0)));
+        assert_eq!(Topology::Reflect.resolve(3, 0, 3, 2), Some((2, 0)));
+        assert_eq!(Topology::Reflect.resolve(6, 2, 3, 2), Some((0, 1)));
+        assert_eq!(Topology::Reflect.resolve(-1, 0, 3, 2), Some((0, 0)));
+        assert_eq!(Topology::NoWrap.resolve(2, 1, 3, 2), Some((2, 1)));
+        assert_eq!(Topology::NoWrap.resolve(3, 1, 3, 2), None);
+    }
+
+    #[test]
+    fn test_torus_cycle() {
+        assert_eq!(torus_cycle(2, 1, 3, 4), 12);
+        assert_eq!(torus_cycle(3, 1, 3, 4), 4);
+        assert_eq!(torus_cycle(0, 2, 3, 4), 2);
+        assert_eq!(torus_cycle(1, 2, 2, 4), 2);
+    }
+}
@@ -0,0 +1,189 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::passport::Passport;
+
+// Something odd about a group of passports taken together, even if each one
+// is valid on its own. Passports are given by their number in the file,
+// counting from 1.
+#[derive(Debug, PartialEq)]
+pub enum Anomaly {
+    DuplicatePid {
+        pid: String,
+        records: Vec<usize>,
+    },
+    SameApartFromCid {
+        records: Vec<usize>,
+    },
+    Cluster {
+        byr: String,
+        hcl: String,
+        records: Vec<usize>,
+    },
+}
+
+impl Anomaly {
+    pub fn records(&self) -> &[usize] {
+        match self {
+            Anomaly::DuplicatePid { records, .. }
+            | Anomaly::SameApartFromCid { records }
+            | Anomaly::Cluster { records, .. } => records,
+        }
+    }
+}
+
+impl fmt::Display for Anomaly {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let records: Vec<String> = self.records().iter().map(|n| n.to_string()).collect();
+        write!(f, "Passports {}: ", records.join(", "))?;
+
+        match self {
+            Anomaly::DuplicatePid { pid, .. } => write!(f, "share the pid {}", pid),
+            Anomaly::SameApartFromCid { .. } => write!(f, "are identical apart from cid"),
+            Anomaly::Cluster { byr, hcl, records } => write!(
+                f,
+                "{} passports born in {} with hair color {}",
+                records.len(),
+                byr,
+                hcl
+            ),
+        }
+    }
+}
+
+// The fields of a passport other than cid, in a fixed order.
+type Body = Vec<(String, String)>;
+
+// Collects what it needs from each passport in turn, so the passports
+// themselves don't have to be kept, and works out the anomalies at the end.
+pub struct AnomalyDetector {
+    cluster_size: usize,
+    pids: HashMap<String, Vec<usize>>,
+    bodies: HashMap<Body, Vec<usize>>,
+    clusters: HashMap<(String, String), Vec<usize>>,
+}
+
+impl AnomalyDetector {
+    // Passports sharing a birth year and hair color are only reported when
+    // there are at least cluster_size of them.
+    pub fn new(cluster_size: usize) -> AnomalyDetector {
+        AnomalyDetector {
+            cluster_size,
+            pids: HashMap::new(),
+            bodies: HashMap::new(),
+            clusters: HashMap::new(),
+        }
+    }
+
+    pub fn add(&mut self, number: usize, passport: &Passport) {
+        if let Some(pid) = passport.get("pid") {
+            self.pids.entry(pid.to_string()).or_default().push(number);
+        }
+
+        let mut body: Body = passport
+            .fields()
+            .iter()
+            .filter(|(name, _)| name != "cid")
+            .cloned()
+            .collect();
+        body.sort();
+        self.bodies.entry(body).or_default().push(number);
+
+        if let (Some(byr), Some(hcl)) = (passport.get("byr"), passport.get("hcl")) {
+            self.clusters
+                .entry((byr.to_string(), hcl.to_string()))
+                .or_default()
+                .push(number);
+        }
+    }
+
+    // Every anomaly found, in order of the first passport involved.
+    pub fn finish(self) -> Vec<Anomaly> {
+        let mut anomalies = vec![];
+
+        for (pid, records) in self.pids {
+            if records.len() > 1 {
+                anomalies.push(Anomaly::DuplicatePid { pid, records });
+            }
+        }
+        for (_, records) in self.bodies {
+            if records.len() > 1 {
+                anomalies.push(Anomaly::SameApartFromCid { records });
+            }
+        }
+        for ((byr, hcl), records) in self.clusters {
+            if records.len() >= self.cluster_size.max(2) {
+                anomalies.push(Anomaly::Cluster { byr, hcl, records });
+            }
+        }
+
+        anomalies.sort_by_key(|anomaly| {
+            let kind = match anomaly {
+                Anomaly::DuplicatePid { .. } => 0,
+                Anomaly::SameApartFromCid { .. } => 1,
+                Anomaly::Cluster { .. } => 2,
+            };
+            (anomaly.records()[0], kind)
+        });
+        anomalies
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::reader::PassportReader;
+
+    #[test]
+    fn test_anomalies() {
+        let input = "pid:1 byr:1950 hcl:#aaaaaa cid:1
+
+byr:1950 hcl:#aaaaaa cid:2 pid:1
+
+pid:2 byr:1950 hcl:#aaaaaa
+
+pid:3 byr:1950 hcl:#bbbbbb
+
+pid:3 byr:1951 hcl:#bbbbbb
+";
+        let mut detector = AnomalyDetector::new(3);
+        for (i, record) in PassportReader::new(input.as_bytes()).enumerate() {
+            detector.add(i + 1, &record.unwrap().passport);
+        }
+
+        assert_eq!(
+            detector.finish(),
+            vec![
+                Anomaly::DuplicatePid {
+                    pid: "1".to_string(),
+                    records: vec![1, 2]
+                },
+                Anomaly::SameApartFromCid {
+                    records: vec![1, 2]
+                },
+                Anomaly::Cluster {
+                    byr: "1950".to_string(),
+                    hcl: "#aaaaaa".to_string(),
+                    records: vec![1, 2, 3]
+                },
+                Anomaly::DuplicatePid {
+                    pid: "3".to_string(),
+                    records: vec![4, 5]
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_display() {
+        let anomaly = Anomaly::Cluster {
+            byr: "1950".to_string(),
+            hcl: "#aaaaaa".to_string(),
+            records: vec![1, 4, 9],
+        };
+        assert_eq!(
+            anomaly.to_string(),
+            "Passports 1, 4, 9: 3 passports born in 1950 with hair color #aaaaaa"
+        );
+    }
+}
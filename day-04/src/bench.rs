@@ -0,0 +1,126 @@
+use std::thread;
+use std::time::Instant;
+
+use crate::fixtures::Random;
+use crate::reader::PassportReader;
+use crate::rules::Ruleset;
+use crate::validator::PassportValidator;
+
+const FIELDS: [&str; 8] = ["byr", "iyr", "eyr", "hgt", "hcl", "ecl", "pid", "cid"];
+
+// A value for the field, out of range about one time in eight.
+fn value(field: &str, random: &mut Random) -> String {
+    let bad = random.next(8) == 0;
+    match field {
+        "byr" => (1900 + random.next(if bad { 200 } else { 100 })).to_string(),
+        "iyr" => (2010 + random.next(if bad { 20 } else { 10 })).to_string(),
+        "eyr" => (2020 + random.next(if bad { 20 } else { 10 })).to_string(),
+        "hgt" if bad => format!("{}", 150 + random.next(40)),
+        "hgt" => format!("{}cm", 150 + random.next(40)),
+        "hcl" if bad => format!("{:06x}", random.next(1 << 24)),
+        "hcl" => format!("#{:06x}", random.next(1 << 24)),
+        "ecl" if bad => "wat".to_string(),
+        "ecl" => {
+            ["amb", "blu", "brn", "gry", "grn", "hzl", "oth"][random.next(7) as usize].to_string()
+        }
+        "pid" if bad => format!("{:010}", random.next(10_000_000_000)),
+        "pid" => format!("{:09}", random.next(1_000_000_000)),
+        _ => random.next(1000).to_string(),
+    }
+}
+
+// Generate passports from a fixed seed so that runs can be compared with each
+// other. Each field is left out about one time in twenty, and the fields are
+// split over one to three lines.
+fn generate(passports: usize) -> String {
+    let mut random = Random::new(0x2020_1204);
+    let mut input = String::new();
+
+    for _ in 0..passports {
+        let mut fields = vec![];
+        for field in FIELDS.iter() {
+            if random.next(20) != 0 {
+                fields.push(format!("{}:{}", field, value(field, &mut random)));
+            }
+        }
+
+        let lines = 1 + random.next(3) as usize;
+        for (i, field) in fields.iter().enumerate() {
+            input.push_str(field);
+            input.push(if (i + 1) % (fields.len() / lines + 1) == 0 {
+                '\n'
+            } else {
+                ' '
+            });
+        }
+        if !input.ends_with('\n') {
+            input.push('\n');
+        }
+        input.push('\n');
+    }
+
+    input
+}
+
+// Time counting the valid passports in a generated file by building a
+// Passport for each one and with the PassportValidator, both on its own and
+// reading the file on one thread and on every available thread, and check
+// that they all agree.
+pub fn run(passports: usize) {
+    let threads = thread::available_parallelism().map_or(1, |threads| threads.get());
+
+    println!("Generating {} passports", passports);
+    let input = generate(passports);
+
+    let rules = Ruleset::default();
+    let start = Instant::now();
+    let passport_valid = PassportReader::new(input.as_bytes())
+        .filter(|record| {
+            record
+                .as_ref()
+                .expect("generated passports can be read")
+                .passport
+                .valid(&rules)
+        })
+        .count();
+    let passport_time = start.elapsed();
+
+    let validator = PassportValidator::new(rules);
+    let records: Vec<&str> = input.split("\n\n").collect();
+    let start = Instant::now();
+    let split_valid = records
+        .iter()
+        .filter(|record| validator.valid(record))
+        .count();
+    let split_time = start.elapsed();
+
+    let start = Instant::now();
+    let single = validator
+        .count_valid(input.as_bytes(), 1)
+        .expect("generated passports can be read");
+    let single_time = start.elapsed();
+
+    let start = Instant::now();
+    let multi = validator
+        .count_valid(input.as_bytes(), threads)
+        .expect("generated passports can be read");
+    let multi_time = start.elapsed();
+
+    assert_eq!(single, multi, "the thread counts disagree");
+    assert_eq!(passport_valid, single.valid, "the validators disagree");
+    assert_eq!(split_valid, single.valid, "the validators disagree");
+
+    println!();
+    println!("Reading into a Passport for each:      {:?}", passport_time);
+    println!("PassportValidator, already split:      {:?}", split_time);
+    println!("PassportValidator, reading, 1 thread:  {:?}", single_time);
+    println!(
+        "PassportValidator, reading, {} threads: {:?}",
+        threads, multi_time
+    );
+    println!();
+    println!(
+        "All of them found {} valid passports out of {}",
+        single.valid, single.total
+    );
+}
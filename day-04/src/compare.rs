@@ -0,0 +1,159 @@
+use std::io;
+
+use crate::passport::ValidationError;
+use crate::reader::Record;
+use crate::rules::Ruleset;
+
+pub struct NamedRuleset {
+    pub name: String,
+    pub rules: Ruleset,
+}
+
+// What one ruleset made of a passport. The errors are everything that made it
+// invalid, so they're empty when it's valid.
+pub struct Verdict {
+    pub valid: bool,
+    pub errors: Vec<ValidationError>,
+}
+
+// A passport that the rulesets didn't all agree on, with each ruleset's
+// verdict in the order the rulesets were given.
+pub struct StatusChange {
+    pub number: usize,
+    pub first_line: usize,
+    pub last_line: usize,
+    pub verdicts: Vec<Verdict>,
+}
+
+pub struct Comparison {
+    pub total: usize,
+    // How many passports each ruleset found valid.
+    pub valid: Vec<usize>,
+    pub changes: Vec<StatusChange>,
+}
+
+impl Comparison {
+    // How many passports became valid and how many became invalid going from
+    // one ruleset to another, given by their positions.
+    pub fn transitions(&self, from: usize, to: usize) -> (usize, usize) {
+        let mut became_valid = 0;
+        let mut became_invalid = 0;
+
+        for change in &self.changes {
+            match (change.verdicts[from].valid, change.verdicts[to].valid) {
+                (false, true) => became_valid += 1,
+                (true, false) => became_invalid += 1,
+                _ => {}
+            }
+        }
+
+        (became_valid, became_invalid)
+    }
+}
+
+// Run every ruleset over the same passports, keeping the ones they disagree
+// about. Only those passports are held on to, so the input can be as long as
+// it likes.
+pub fn compare<I>(rulesets: &[NamedRuleset], records: I) -> io::Result<Comparison>
+where
+    I: Iterator<Item = io::Result<Record>>,
+{
+    let mut comparison = Comparison {
+        total: 0,
+        valid: vec![0; rulesets.len()],
+        changes: vec![],
+    };
+
+    for record in records {
+        let record = record?;
+        comparison.total += 1;
+
+        let valid: Vec<bool> = rulesets
+            .iter()
+            .map(|ruleset| record.passport.valid(&ruleset.rules))
+            .collect();
+
+        for (count, &valid) in comparison.valid.iter_mut().zip(&valid) {
+            if valid {
+                *count += 1;
+            }
+        }
+
+        if valid.windows(2).all(|pair| pair[0] == pair[1]) {
+            continue;
+        }
+
+        let verdicts = rulesets
+            .iter()
+            .zip(valid)
+            .map(|(ruleset, valid)| Verdict {
+                valid,
+                errors: record.passport.validate(&ruleset.rules),
+            })
+            .collect();
+
+        comparison.changes.push(StatusChange {
+            number: comparison.total,
+            first_line: record.first_line,
+            last_line: record.last_line,
+            verdicts,
+        });
+    }
+
+    Ok(comparison)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::reader::PassportReader;
+
+    const INPUT: &str = "byr:1937 iyr:2017 eyr:2020 hgt:183cm hcl:#fffffd ecl:gry pid:860033327
+
+byr:1937 iyr:2017 eyr:2020 hgt:183 hcl:#fffffd ecl:gry pid:860033327
+
+byr:1937 iyr:2017 eyr:2020 hgt:183cm hcl:#fffffd ecl:gry
+
+byr:1937 iyr:2017 eyr:2020 hgt:183cm hcl:#fffffd ecl:gry ecl:zzz pid:860033327
+";
+
+    fn rulesets() -> Vec<NamedRuleset> {
+        let as_of = "2020-12-04".parse().unwrap();
+        vec![
+            NamedRuleset {
+                name: "part1".to_string(),
+                rules: Ruleset::named("part1", as_of).unwrap(),
+            },
+            NamedRuleset {
+                name: "part2".to_string(),
+                rules: Ruleset::named("part2", as_of).unwrap(),
+            },
+        ]
+    }
+
+    #[test]
+    fn test_compare() {
+        let comparison = compare(&rulesets(), PassportReader::new(INPUT.as_bytes())).unwrap();
+
+        assert_eq!(comparison.total, 4);
+        assert_eq!(comparison.valid, vec![3, 1]);
+        assert_eq!(comparison.changes.len(), 2);
+
+        let change = &comparison.changes[0];
+        assert_eq!(
+            (change.number, change.first_line, change.last_line),
+            (2, 3, 3)
+        );
+        assert!(change.verdicts[0].valid);
+        assert!(change.verdicts[0].errors.is_empty());
+        assert!(!change.verdicts[1].valid);
+        assert_eq!(
+            change.verdicts[1].errors[0].to_string(),
+            "hgt=183: missing unit (rule: measurement 150-293cm or 59-76in)"
+        );
+
+        assert_eq!(comparison.changes[1].number, 4);
+        assert_eq!(comparison.transitions(0, 1), (0, 2));
+        assert_eq!(comparison.transitions(1, 0), (2, 0));
+    }
+}
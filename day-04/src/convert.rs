@@ -0,0 +1,466 @@
+use std::fmt;
+use std::io;
+use std::io::{BufRead, Write};
+use std::str::FromStr;
+
+use serde_json::{Map, Value};
+
+use crate::reader::Record;
+use crate::rules::Ruleset;
+
+// Separates the lines of a passport in its layout. A lone / can't be a field,
+// since fields always have a ':' in them.
+const LINE_BREAK: &str = " / ";
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Format {
+    JsonLines,
+    Csv,
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Format, String> {
+        match value {
+            "jsonl" => Ok(Format::JsonLines),
+            "csv" => Ok(Format::Csv),
+            _ => Err(format!("unknown format {}, expected jsonl or csv", value)),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct ConvertError(String);
+
+impl fmt::Display for ConvertError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl From<io::Error> for ConvertError {
+    fn from(error: io::Error) -> ConvertError {
+        ConvertError(error.to_string())
+    }
+}
+
+impl From<csv::Error> for ConvertError {
+    fn from(error: csv::Error) -> ConvertError {
+        ConvertError(error.to_string())
+    }
+}
+
+impl From<serde_json::Error> for ConvertError {
+    fn from(error: serde_json::Error) -> ConvertError {
+        ConvertError(error.to_string())
+    }
+}
+
+// One exported passport. There's a column for each field in the ruleset, in
+// the ruleset's order, holding the first non-empty value given for it. The
+// layout lists the fields in the order they were given, with the lines they
+// were on, so the passport can be written back out as it was. Fields that
+// don't fit in a column, because they're repeated, empty or unknown, are
+// written into the layout whole, and so are malformed tokens, with a \ in
+// front of any that have no ':' so they can't be taken for a column.
+struct Row {
+    values: Vec<Option<String>>,
+    layout: String,
+    verdict: Option<(bool, Vec<String>)>,
+}
+
+impl Row {
+    fn new(record: &Record, rules: &Ruleset, validity: bool) -> Row {
+        let mut values: Vec<Option<String>> = vec![None; rules.fields.len()];
+        let fields = record.passport.fields();
+        let mut malformed = record.malformed.iter().peekable();
+        let mut field = 0;
+        let mut lines = vec![];
+
+        for (i, &count) in record.fields_per_line.iter().enumerate() {
+            let line = record.first_line + i;
+            let end = field + count;
+            let mut tokens = vec![];
+            loop {
+                while let Some(token) =
+                    malformed.next_if(|token| token.line == line && token.field <= field)
+                {
+                    if token.token.contains(':') {
+                        tokens.push(token.token.clone());
+                    } else {
+                        tokens.push(format!("\\{}", token.token));
+                    }
+                }
+                if field == end {
+                    break;
+                }
+
+                let (name, value) = &fields[field];
+                let column = rules.fields.iter().position(|rule| rule.name == *name);
+                match column {
+                    Some(i) if values[i].is_none() && !value.is_empty() => {
+                        values[i] = Some(value.clone());
+                        tokens.push(name.clone());
+                    }
+                    _ => tokens.push(format!("{}:{}", name, value)),
+                }
+                field += 1;
+            }
+            lines.push(tokens.join(" "));
+        }
+
+        let verdict = if validity {
+            let errors = record.passport.validate(rules);
+            Some((
+                errors.is_empty(),
+                errors.iter().map(|error| error.to_string()).collect(),
+            ))
+        } else {
+            None
+        };
+
+        Row {
+            values,
+            layout: lines.join(LINE_BREAK),
+            verdict,
+        }
+    }
+}
+
+// Write the passports as JSON Lines or CSV, optionally with whether each one
+// is valid and why not. Returns how many passports weren't canonical, which
+// importing won't give back exactly as they were: their fields come back
+// separated by single spaces, with one blank line between passports.
+pub fn export<I, W>(
+    records: I,
+    rules: &Ruleset,
+    format: Format,
+    validity: bool,
+    mut output: W,
+) -> Result<usize, ConvertError>
+where
+    I: Iterator<Item = io::Result<Record>>,
+    W: Write,
+{
+    let mut columns: Vec<&str> = rules.fields.iter().map(|rule| rule.name.as_str()).collect();
+    columns.push("layout");
+    if validity {
+        columns.push("valid");
+        columns.push("errors");
+    }
+
+    let mut not_canonical = 0;
+
+    match format {
+        Format::JsonLines => {
+            for record in records {
+                let record = record?;
+                not_canonical += usize::from(!record.canonical);
+                let row = Row::new(&record, rules, validity);
+                let mut object = Map::new();
+                for (rule, value) in rules.fields.iter().zip(row.values) {
+                    object.insert(rule.name.clone(), value.map_or(Value::Null, Value::String));
+                }
+                object.insert("layout".to_string(), Value::String(row.layout));
+                if let Some((valid, errors)) = row.verdict {
+                    object.insert("valid".to_string(), Value::Bool(valid));
+                    object.insert(
+                        "errors".to_string(),
+                        Value::Array(errors.into_iter().map(Value::String).collect()),
+                    );
+                }
+                serde_json::to_writer(&mut output, &object)?;
+                writeln!(output)?;
+            }
+        }
+        Format::Csv => {
+            let mut writer = csv::Writer::from_writer(output);
+            writer.write_record(&columns)?;
+            for record in records {
+                let record = record?;
+                not_canonical += usize::from(!record.canonical);
+                let row = Row::new(&record, rules, validity);
+                let mut cells: Vec<String> = row
+                    .values
+                    .into_iter()
+                    .map(|value| value.unwrap_or_default())
+                    .collect();
+                cells.push(row.layout);
+                if let Some((valid, errors)) = row.verdict {
+                    cells.push(valid.to_string());
+                    cells.push(errors.join("; "));
+                }
+                writer.write_record(&cells)?;
+            }
+            writer.flush()?;
+        }
+    }
+
+    Ok(not_canonical)
+}
+
+// Read passports back from JSON Lines or CSV and write them out in the
+// puzzle's format, one blank line between each. Any valid and errors columns
+// are ignored. Without a layout, a passport's fields are written on one line
+// in the order of the columns.
+pub fn import<R, W>(input: R, format: Format, mut output: W) -> Result<usize, ConvertError>
+where
+    R: BufRead,
+    W: Write,
+{
+    let rows = match format {
+        Format::JsonLines => json_rows(input)?,
+        Format::Csv => csv_rows(input)?,
+    };
+
+    for (i, row) in rows.iter().enumerate() {
+        let passport = passport_text(row)
+            .map_err(|message| ConvertError(format!("row {}: {}", i + 1, message)))?;
+        if i > 0 {
+            writeln!(output)?;
+        }
+        writeln!(output, "{}", passport)?;
+    }
+
+    Ok(rows.len())
+}
+
+// Each row's columns in order, leaving out the empty ones.
+type Columns = Vec<(String, String)>;
+
+fn json_rows<R: BufRead>(input: R) -> Result<Vec<Columns>, ConvertError> {
+    let mut rows = vec![];
+
+    for (i, line) in input.lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let object: Map<String, Value> = serde_json::from_str(&line)
+            .map_err(|error| ConvertError(format!("line {}: {}", i + 1, error)))?;
+
+        let mut columns = vec![];
+        for (name, value) in object {
+            let value = match value {
+                Value::Null => continue,
+                Value::String(value) => value,
+                Value::Number(number) => number.to_string(),
+                Value::Bool(_) | Value::Array(_) if ignored(&name) => continue,
+                _ => {
+                    return Err(ConvertError(format!(
+                        "line {}: {} isn't a string",
+                        i + 1,
+                        name
+                    )))
+                }
+            };
+            columns.push((name, value));
+        }
+        rows.push(columns);
+    }
+
+    Ok(rows)
+}
+
+fn csv_rows<R: BufRead>(input: R) -> Result<Vec<Columns>, ConvertError> {
+    let mut reader = csv::Reader::from_reader(input);
+    let headers = reader.headers()?.clone();
+    let mut rows = vec![];
+
+    for record in reader.records() {
+        let record = record?;
+        let columns = headers
+            .iter()
+            .zip(record.iter())
+            .filter(|(_, value)| !value.is_empty())
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect();
+        rows.push(columns);
+    }
+
+    Ok(rows)
+}
+
+fn ignored(column: &str) -> bool {
+    column == "valid" || column == "errors"
+}
+
+fn passport_text(row: &[(String, String)]) -> Result<String, String> {
+    let mut fields: Vec<(&str, &str, bool)> = row
+        .iter()
+        .filter(|(name, _)| name != "layout" && !ignored(name))
+        .map(|(name, value)| (name.as_str(), value.as_str(), false))
+        .collect();
+
+    let layout = match row.iter().find(|(name, _)| name == "layout") {
+        Some((_, layout)) => layout,
+        None => {
+            let tokens: Vec<String> = fields
+                .iter()
+                .map(|(name, value, _)| format!("{}:{}", name, value))
+                .collect();
+            return Ok(tokens.join(" "));
+        }
+    };
+
+    let mut lines = vec![];
+    for line in layout.split(LINE_BREAK) {
+        let mut tokens = vec![];
+        for token in line.split(' ') {
+            if token.contains(':') {
+                tokens.push(token.to_string());
+                continue;
+            }
+            if let Some(token) = token.strip_prefix('\\') {
+                tokens.push(token.to_string());
+                continue;
+            }
+
+            let field = fields
+                .iter_mut()
+                .find(|(name, _, used)| *name == token && !used)
+                .ok_or(format!(
+                    "the layout has {} but there's no value for it",
+                    token
+                ))?;
+            field.2 = true;
+            tokens.push(format!("{}:{}", field.0, field.1));
+        }
+        lines.push(tokens.join(" "));
+    }
+
+    if let Some((name, _, _)) = fields.iter().find(|(_, _, used)| !used) {
+        return Err(format!("{} has a value but isn't in the layout", name));
+    }
+
+    Ok(lines.join("\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::reader::PassportReader;
+
+    const TRICKY: &str = "byr:1937 iyr:2017 eyr:2020
+hgt:183cm hcl:#fffffd ecl:gry pid:860033327 pid:860033327
+
+byr: zzz:1 byr:1920 / hcl:a/b,\"c\" cid: \\x
+oops :1
+
+ecl:blu
+";
+
+    // The imported text, and how many passports export said wouldn't come
+    // back exactly.
+    fn round_trip(input: &str, format: Format, validity: bool) -> (String, usize) {
+        let rules = Ruleset::default();
+        let mut exported = vec![];
+        let not_canonical = export(
+            PassportReader::new(input.as_bytes()),
+            &rules,
+            format,
+            validity,
+            &mut exported,
+        )
+        .unwrap();
+
+        let mut imported = vec![];
+        import(exported.as_slice(), format, &mut imported).unwrap();
+        (String::from_utf8(imported).unwrap(), not_canonical)
+    }
+
+    #[test]
+    fn test_round_trip() {
+        for &input in &[include_str!("../input.txt"), TRICKY] {
+            for &format in &[Format::JsonLines, Format::Csv] {
+                for &validity in &[false, true] {
+                    assert_eq!(round_trip(input, format, validity), (input.to_string(), 0));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_round_trip_not_canonical() {
+        let input = "ecl:gry pid:860033327 oops\nbyr:1937\tiyr:2017\n\n\nhgt:183cm\n";
+
+        for &format in &[Format::JsonLines, Format::Csv] {
+            assert_eq!(
+                round_trip(input, format, false),
+                (
+                    "ecl:gry pid:860033327 oops\nbyr:1937 iyr:2017\n\nhgt:183cm\n".to_string(),
+                    1
+                )
+            );
+        }
+    }
+
+    #[test]
+    fn test_export() {
+        let rules = Ruleset::default();
+        let mut csv = vec![];
+        export(
+            PassportReader::new(TRICKY.as_bytes()),
+            &rules,
+            Format::Csv,
+            true,
+            &mut csv,
+        )
+        .unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+
+        assert_eq!(
+            lines[0],
+            "byr,iyr,eyr,hgt,hcl,ecl,pid,cid,layout,valid,errors"
+        );
+        assert_eq!(
+            lines[1],
+            "1937,2017,2020,183cm,#fffffd,gry,860033327,,byr iyr eyr / hgt hcl ecl pid pid:860033327,true,"
+        );
+
+        let mut jsonl = vec![];
+        export(
+            PassportReader::new("ecl:blu\n".as_bytes()),
+            &rules,
+            Format::JsonLines,
+            false,
+            &mut jsonl,
+        )
+        .unwrap();
+        assert_eq!(
+            String::from_utf8(jsonl).unwrap(),
+            "{\"byr\":null,\"iyr\":null,\"eyr\":null,\"hgt\":null,\"hcl\":null,\"ecl\":\"blu\",\"pid\":null,\"cid\":null,\"layout\":\"ecl\"}\n"
+        );
+    }
+
+    #[test]
+    fn test_import_without_layout() {
+        let mut imported = vec![];
+        let count = import(
+            "pid,byr,notes\n087499704,1980,\n,,x\n".as_bytes(),
+            Format::Csv,
+            &mut imported,
+        )
+        .unwrap();
+
+        assert_eq!(count, 2);
+        assert_eq!(
+            String::from_utf8(imported).unwrap(),
+            "pid:087499704 byr:1980\n\nnotes:x\n"
+        );
+
+        let error = import(
+            "{\"byr\":\"1980\",\"pid\":\"1\",\"layout\":\"byr\"}\n".as_bytes(),
+            Format::JsonLines,
+            vec![],
+        )
+        .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "row 1: pid has a value but isn't in the layout"
+        );
+    }
+}
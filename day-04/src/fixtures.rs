@@ -0,0 +1,442 @@
+use std::fmt;
+use std::io;
+use std::io::Write;
+use std::str::FromStr;
+
+// The fields the 2020 rules require, in the order they list them. Fixtures
+// are always generated for those rules.
+const REQUIRED: [&str; 7] = ["byr", "iyr", "eyr", "hgt", "hcl", "ecl", "pid"];
+
+const EYE_COLORS: [&str; 7] = ["amb", "blu", "brn", "gry", "grn", "hzl", "oth"];
+
+// A xorshift generator, which is plenty random enough for generating
+// passports and gives the same sequence for the same seed everywhere.
+pub struct Random(u64);
+
+impl Random {
+    pub fn new(seed: u64) -> Random {
+        // Xorshift gets stuck on zero.
+        Random(if seed == 0 { 0x2020_1204 } else { seed })
+    }
+
+    pub fn next(&mut self, below: u64) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0 % below
+    }
+
+    fn pick<'a>(&mut self, values: &[&'a str]) -> &'a str {
+        values[self.next(values.len() as u64) as usize]
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Problem {
+    Missing,
+    Invalid,
+}
+
+// The rule an invalid passport breaks: one of the required fields is either
+// left out or given a value that doesn't pass. Written as missing-byr,
+// invalid-hgt and so on.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Violation {
+    pub field: &'static str,
+    pub problem: Problem,
+}
+
+impl Violation {
+    // Every rule there is to break.
+    pub fn all() -> Vec<Violation> {
+        let mut violations = vec![];
+        for &problem in &[Problem::Missing, Problem::Invalid] {
+            for &field in REQUIRED.iter() {
+                violations.push(Violation { field, problem });
+            }
+        }
+        violations
+    }
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let problem = match self.problem {
+            Problem::Missing => "missing",
+            Problem::Invalid => "invalid",
+        };
+        write!(f, "{}-{}", problem, self.field)
+    }
+}
+
+impl FromStr for Violation {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Violation, String> {
+        Violation::all()
+            .into_iter()
+            .find(|violation| violation.to_string() == value)
+            .ok_or(format!(
+                "unknown rule {}, expected missing-<field> or invalid-<field> for one of {}",
+                value,
+                REQUIRED.join(" ")
+            ))
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FieldOrder {
+    // The order the rules list the fields in, with cid last.
+    Fixed,
+    Shuffled,
+}
+
+impl FromStr for FieldOrder {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<FieldOrder, String> {
+        match value {
+            "fixed" => Ok(FieldOrder::Fixed),
+            "shuffled" => Ok(FieldOrder::Shuffled),
+            _ => Err(format!(
+                "unknown field order {}, expected fixed or shuffled",
+                value
+            )),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Wrap {
+    // At most this many fields on each line.
+    Fields(usize),
+    // Spread over one to four lines.
+    Random,
+}
+
+impl FromStr for Wrap {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Wrap, String> {
+        match value {
+            "random" => Ok(Wrap::Random),
+            fields => fields
+                .parse::<usize>()
+                .ok()
+                .filter(|&fields| fields > 0)
+                .map(Wrap::Fields)
+                .ok_or(format!(
+                    "--wrap must be random or a positive number of fields, not {}",
+                    value
+                )),
+        }
+    }
+}
+
+pub struct FixtureOptions {
+    pub seed: u64,
+    pub passports: usize,
+    // The fraction of passports that are valid. They're spread evenly through
+    // the file, so there are always exactly this many, rounded down.
+    pub valid_ratio: f64,
+    pub field_order: FieldOrder,
+    pub wrap: Wrap,
+    // The rules the invalid passports break, each one breaking one of them
+    // picked at random.
+    pub violations: Vec<Violation>,
+    // The chance of each passport having a cid.
+    pub cid_ratio: f64,
+}
+
+impl Default for FixtureOptions {
+    fn default() -> FixtureOptions {
+        FixtureOptions {
+            seed: 0x2020_1204,
+            passports: 1000,
+            valid_ratio: 0.5,
+            field_order: FieldOrder::Shuffled,
+            wrap: Wrap::Random,
+            violations: Violation::all(),
+            cid_ratio: 0.5,
+        }
+    }
+}
+
+// A generated passport as it's written in the file, along with whether the
+// 2020 rules should find it valid: they should unless it has a violation.
+pub struct Fixture {
+    pub text: String,
+    pub violation: Option<Violation>,
+}
+
+impl Fixture {
+    pub fn valid(&self) -> bool {
+        self.violation.is_none()
+    }
+}
+
+// A value that passes the 2020 rules, anywhere in their range.
+fn valid_value(field: &str, random: &mut Random) -> String {
+    match field {
+        "byr" => (1920 + random.next(101)).to_string(),
+        "iyr" => (2010 + random.next(11)).to_string(),
+        "eyr" => (2020 + random.next(11)).to_string(),
+        "hgt" if random.next(2) == 0 => format!("{}cm", 150 + random.next(144)),
+        "hgt" => format!("{}in", 59 + random.next(18)),
+        "hcl" => format!("#{:06x}", random.next(1 << 24)),
+        "ecl" => random.pick(&EYE_COLORS).to_string(),
+        "pid" => format!("{:09}", random.next(1_000_000_000)),
+        _ => (100 + random.next(250)).to_string(),
+    }
+}
+
+// A year that's out of range, or doesn't have four digits.
+fn invalid_year(min: u64, max: u64, random: &mut Random) -> String {
+    match random.next(3) {
+        0 => (min - 1 - random.next(20)).to_string(),
+        1 => (max + 1 + random.next(20)).to_string(),
+        _ => format!("{}0", min + random.next(max - min + 1)),
+    }
+}
+
+// A value that breaks the 2020 rules in one of the ways a real one might.
+fn invalid_value(field: &str, random: &mut Random) -> String {
+    match field {
+        "byr" => invalid_year(1920, 2020, random),
+        "iyr" => invalid_year(2010, 2020, random),
+        "eyr" => invalid_year(2020, 2030, random),
+        "hgt" => match random.next(5) {
+            0 => format!("{}cm", 100 + random.next(50)),
+            1 => format!("{}cm", 294 + random.next(50)),
+            2 => format!("{}in", 40 + random.next(19)),
+            3 => format!("{}", 150 + random.next(40)),
+            _ => format!("{}mm", 1500 + random.next(400)),
+        },
+        "hcl" => match random.next(4) {
+            0 => format!("{:06x}", random.next(1 << 24)),
+            1 => format!("#{:05x}", random.next(1 << 20)),
+            2 => format!("#{:05x}z", random.next(1 << 20)),
+            _ => format!("#{:07x}", random.next(1 << 28)),
+        },
+        "ecl" => random
+            .pick(&["wat", "blue", "BRN", "xry", "gray"])
+            .to_string(),
+        "pid" => match random.next(3) {
+            0 => format!("{:08}", random.next(100_000_000)),
+            1 => format!("{:010}", random.next(10_000_000_000)),
+            _ => format!("{:08}x", random.next(100_000_000)),
+        },
+        _ => panic!("{} isn't a required field", field),
+    }
+}
+
+fn generate_one(
+    options: &FixtureOptions,
+    violation: Option<Violation>,
+    random: &mut Random,
+) -> Fixture {
+    let mut fields = vec![];
+    for &field in REQUIRED.iter() {
+        let value = match violation {
+            Some(violation) if violation.field == field => match violation.problem {
+                Problem::Missing => continue,
+                Problem::Invalid => invalid_value(field, random),
+            },
+            _ => valid_value(field, random),
+        };
+        fields.push(format!("{}:{}", field, value));
+    }
+    if (random.next(1_000_000) as f64) < options.cid_ratio * 1_000_000.0 {
+        fields.push(format!("cid:{}", valid_value("cid", random)));
+    }
+
+    if options.field_order == FieldOrder::Shuffled {
+        for i in (1..fields.len()).rev() {
+            fields.swap(i, random.next(i as u64 + 1) as usize);
+        }
+    }
+
+    let per_line = match options.wrap {
+        Wrap::Fields(per_line) => per_line,
+        Wrap::Random => fields.len().div_ceil(1 + random.next(4) as usize),
+    };
+    let lines: Vec<String> = fields.chunks(per_line).map(|line| line.join(" ")).collect();
+
+    Fixture {
+        text: lines.join("\n"),
+        violation,
+    }
+}
+
+// Generate passports from the seed, so the same options always give the same
+// file.
+pub fn generate(options: &FixtureOptions) -> Vec<Fixture> {
+    let mut random = Random::new(options.seed);
+    let ratio = options.valid_ratio.clamp(0.0, 1.0);
+
+    (0..options.passports)
+        .map(|i| {
+            // Valid whenever the running count of valid passports goes up.
+            let valid = ((i + 1) as f64 * ratio).floor() > (i as f64 * ratio).floor();
+            let violation = if valid || options.violations.is_empty() {
+                None
+            } else {
+                let i = random.next(options.violations.len() as u64) as usize;
+                Some(options.violations[i])
+            };
+            generate_one(options, violation, &mut random)
+        })
+        .collect()
+}
+
+// Write the passports in the puzzle's format, and the expected verdict for
+// each one to a separate file, one line per passport: its number, then valid,
+// or invalid and the rule it breaks.
+pub fn write<P: Write, E: Write>(
+    fixtures: &[Fixture],
+    mut passports: P,
+    mut expected: E,
+) -> io::Result<()> {
+    for (i, fixture) in fixtures.iter().enumerate() {
+        if i > 0 {
+            writeln!(passports)?;
+        }
+        writeln!(passports, "{}", fixture.text)?;
+
+        match fixture.violation {
+            None => writeln!(expected, "{} valid", i + 1)?,
+            Some(violation) => writeln!(expected, "{} invalid {}", i + 1, violation)?,
+        }
+    }
+    passports.flush()?;
+    expected.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::passport::ValidationError;
+    use crate::reader::PassportReader;
+    use crate::rules::Ruleset;
+
+    #[test]
+    fn test_verdicts() {
+        let rules = Ruleset::default();
+
+        for seed in 1..=20 {
+            for &field_order in &[FieldOrder::Fixed, FieldOrder::Shuffled] {
+                for &wrap in &[Wrap::Fields(1), Wrap::Fields(3), Wrap::Random] {
+                    let options = FixtureOptions {
+                        seed,
+                        passports: 200,
+                        field_order,
+                        wrap,
+                        ..FixtureOptions::default()
+                    };
+                    let fixtures = generate(&options);
+
+                    let mut text = vec![];
+                    write(&fixtures, &mut text, io::sink()).unwrap();
+                    let records: Vec<_> = PassportReader::new(text.as_slice())
+                        .map(|record| record.unwrap())
+                        .collect();
+                    assert_eq!(records.len(), fixtures.len());
+
+                    for (record, fixture) in records.iter().zip(&fixtures) {
+                        assert!(record.malformed.is_empty());
+                        assert_eq!(
+                            record.passport.valid(&rules),
+                            fixture.valid(),
+                            "{}",
+                            fixture.text
+                        );
+
+                        // The only thing wrong is the rule that was broken.
+                        if let Some(violation) = fixture.violation {
+                            let errors = record.passport.validate(&rules);
+                            assert_eq!(errors.len(), 1, "{}", fixture.text);
+                            let field = match &errors[0] {
+                                ValidationError::Missing { field } => {
+                                    assert_eq!(violation.problem, Problem::Missing);
+                                    field
+                                }
+                                ValidationError::Invalid { field, .. } => {
+                                    assert_eq!(violation.problem, Problem::Invalid);
+                                    field
+                                }
+                                error => panic!("unexpected error {}", error),
+                            };
+                            assert_eq!(field, violation.field);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_options() {
+        let options = FixtureOptions {
+            passports: 100,
+            valid_ratio: 0.25,
+            field_order: FieldOrder::Fixed,
+            wrap: Wrap::Fields(4),
+            violations: vec!["invalid-hgt".parse().unwrap()],
+            cid_ratio: 0.0,
+            ..FixtureOptions::default()
+        };
+        let fixtures = generate(&options);
+
+        assert_eq!(
+            fixtures.iter().filter(|fixture| fixture.valid()).count(),
+            25
+        );
+        for fixture in &fixtures {
+            assert!(!fixture.text.contains("cid"));
+            let lines: Vec<&str> = fixture.text.lines().collect();
+            assert_eq!(lines.len(), 2);
+            assert!(lines[0].starts_with("byr:"));
+            assert!(lines[1].starts_with("hcl:"));
+            if let Some(violation) = fixture.violation {
+                assert_eq!(violation.to_string(), "invalid-hgt");
+            }
+        }
+
+        let all_cid = FixtureOptions {
+            cid_ratio: 1.0,
+            ..FixtureOptions::default()
+        };
+        assert!(generate(&all_cid)
+            .iter()
+            .all(|fixture| fixture.text.contains("cid:")));
+
+        // The same seed always gives the same passports.
+        let texts = |seed| -> Vec<String> {
+            let options = FixtureOptions {
+                seed,
+                ..FixtureOptions::default()
+            };
+            generate(&options)
+                .into_iter()
+                .map(|fixture| fixture.text)
+                .collect()
+        };
+        assert_eq!(texts(7), texts(7));
+        assert_ne!(texts(7), texts(8));
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            "missing-byr".parse(),
+            Ok(Violation {
+                field: "byr",
+                problem: Problem::Missing
+            })
+        );
+        assert!("missing-cid".parse::<Violation>().is_err());
+        assert_eq!("3".parse(), Ok(Wrap::Fields(3)));
+        assert!("0".parse::<Wrap>().is_err());
+        assert_eq!("shuffled".parse(), Ok(FieldOrder::Shuffled));
+    }
+}
@@ -21,58 +21,465 @@
 // pid (Passport ID) - a nine-digit number, including leading zeros
 // cid (Country ID) - optional, so no validation
 //
-// Usage: cargo run <input-file>
+// Rulesets:
+//
+// The rules above live in rules/2020.toml, which is built in and used by
+// default. A different ruleset file can be given with --rules to change which
+// fields are required and how their values are checked without recompiling.
+// See rules/2020.toml for the format.
+//
+// Relative years:
+//
+// Years in a ruleset can also be given relative to an as-of date, such as a
+// holder's age being from 0 to 100 or a passport not having expired by then.
+// The as-of date is today unless it's given with --as-of YYYY-MM-DD, which
+// only goes with --rules or --compare since the default rules use fixed years.
+// The built in current ruleset is the 2020 rules written that way, so it's the
+// same as the default rules as of 2020; see rules/current.toml. Passports only
+// give years, so the rules only go by the as-of year: a passport expiring in
+// that year hasn't expired yet, even if the as-of date is 31 December.
+//
+// Repeated and unknown fields:
+//
+// Every occurrence of a field is kept. A field given more than once with the
+// same value is a duplicate, and one given different values is a conflict.
+// Fields the ruleset doesn't list are unknown. The ruleset's strictness table
+// decides which of these make a passport invalid, and --strict makes all of
+// them do so.
+//
+// Reports:
+//
+// With --report every invalid passport is listed with its number in the file
+// and the lines it came from, followed by each missing field and each invalid
+// value along with why it was rejected and the rule it broke. Tokens that
+// aren't field:value pairs are listed too, with the line they were on, even
+// when the passport is valid without them. Repeated and unknown fields that
+// the rules allow are listed as warnings.
+//
+// Suggestions:
+//
+// With --suggest the report also proposes a likely intended value for each
+// invalid value that's nearly right: the missing unit on a height when only
+// one unit's range fits the number, the closest allowed value to a misspelled
+// one, a value in the wrong case, a missing # or an id short of its leading
+// zeros. A suggestion is only made when exactly one fix passes the rule, and
+// they're only reported. Passports are always counted as they were given.
+//
+// Anomalies:
+//
+// With --anomalies the passports are also checked against each other, valid
+// or not, and anything suspicious is listed by passport number: passports
+// sharing a pid, passports identical apart from their cid, and clusters of
+// passports with the same birth year and hair color. A cluster has to have at
+// least 3 passports in it, or however many are given with --cluster-size.
+//
+// Reading:
+//
+// Passports are read one at a time rather than loading the whole file. Fields
+// can be separated by any mix of spaces and tabs, lines can end in \n or \r\n,
+// and any number of blank lines can separate passports.
+//
+// Typed passports:
+//
+// With --typed every passport that passes the 2020 rules is parsed into a
+// ValidatedPassport, with real types for years, heights, colors and ids, and
+// written back out on one line with its fields in a fixed order. The range of
+// heights is printed after them, converted to both units.
+//
+// Comparing rulesets:
+//
+// --compare runs several rulesets over the same file. Each one is given as
+// name=file, or just a name for one of the built in rulesets: part1, which
+// only checks that the fields are there, part2, the default rules, and
+// current, the default rules relative to the as-of date. Every
+// passport the rulesets don't all agree on is listed with what each ruleset
+// made of it and why, followed by how many passports each one found valid and
+// how many changed status going from the first ruleset to each of the others.
+//
+// JSON and CSV:
+//
+// --export jsonl|csv writes the passports out as JSON Lines or CSV, with a
+// column for each field in the ruleset in the ruleset's order and a layout
+// column recording the order and lines the fields were given in. Repeated,
+// empty and unknown fields and malformed tokens are kept in the layout.
+// --validity adds columns for whether each passport is valid and why not.
+// --import jsonl|csv turns such a file back into the puzzle's format, with
+// single spaces between fields and one blank line between passports. That's
+// exactly as it was for files already written that way, and --export says how
+// many passports weren't. Other tools can leave the layout out, in which case
+// each passport's fields are written on one line in column order.
+//
+// Large files:
+//
+// With --threads <n> the passports are only counted, using a
+// PassportValidator that checks each passport straight from its text on n
+// threads instead of building a Passport for each one. It always agrees with
+// the regular count. --bench <passports> generates that many passports and
+// compares how long counting them takes each way.
+//
+// Generating passports:
+//
+// --generate <passports> writes a file of made up passports to --output, and
+// the verdict the 2020 rules should reach on each one to the same file name
+// with .expected on the end. The same --seed always gives the same file.
+// Half of the passports are valid unless --valid-ratio says otherwise, and
+// each invalid one breaks exactly one rule, picked at random from those given
+// with --violate, such as missing-byr or invalid-hgt, or from all of them.
+// The fields are shuffled unless --field-order is fixed, --wrap puts at most
+// that many on each line instead of spreading them over one to four lines,
+// and --cid-ratio is the chance of a passport having a cid, a half by
+// default.
+//
+// Usage: cargo run <input-file> [--rules <ruleset-file> [--as-of <YYYY-MM-DD>]] [--strict]
+//                  [--report] [--suggest] [--typed] [--anomalies [--cluster-size <n>]]
+//        cargo run <input-file> --compare <name>[=<ruleset-file>]... [--as-of <YYYY-MM-DD>]
+//                  [--strict]
+//        cargo run --release -- <input-file> --threads <n>
+//                  [--rules <ruleset-file> [--as-of <YYYY-MM-DD>]] [--strict]
+//        cargo run <input-file> --export jsonl|csv [--validity]
+//                  [--rules <ruleset-file> [--as-of <YYYY-MM-DD>]] [--strict]
+//        cargo run <jsonl-or-csv-file> --import jsonl|csv
+//        cargo run --release -- --bench <passports>
+//        cargo run -- --generate <passports> --output <file> [--seed <n>] [--valid-ratio <r>]
+//                  [--field-order fixed|shuffled] [--wrap <fields>|random]
+//                  [--violate <rule>]... [--cid-ratio <r>]
+
+use std::{
+    convert::TryFrom,
+    env, fs,
+    fs::File,
+    io,
+    io::{BufReader, BufWriter},
+};
+
+mod anomalies;
+use crate::anomalies::AnomalyDetector;
+
+mod bench;
 
-use std::{collections::HashMap, env, fs::File, io::BufRead, io::BufReader};
+mod compare;
+use crate::compare::{Comparison, NamedRuleset};
+
+mod convert;
+
+mod date;
+use crate::date::Date;
+
+mod fixtures;
+
+mod options;
+use crate::options::Options;
 
 mod passport;
-use crate::passport::Passport;
+
+mod reader;
+use crate::reader::{PassportReader, Record};
+
+mod suggest;
+
+mod rules;
+use crate::rules::{Ruleset, Strictness};
+
+mod validated;
+use crate::validated::{Height, ValidatedPassport};
+
+mod validator;
+use crate::validator::PassportValidator;
 
 fn main() {
-    let args: Vec<String> = env::args().collect();
+    let args: Vec<String> = env::args().skip(1).collect();
+
+    let options = match Options::parse(&args) {
+        Ok(options) => options,
+        Err(message) => {
+            println!("{}", message);
+            println!("{}", options::USAGE);
+            return;
+        }
+    };
+
+    if let Some(passports) = options.bench {
+        bench::run(passports);
+        return;
+    }
 
-    if args.len() < 2 {
-        println!("Usage: cargo run <input-file>");
+    if let Some(fixture_options) = &options.generate {
+        let output = options
+            .output
+            .as_deref()
+            .expect("an output file is required with --generate");
+        let expected = format!("{}.expected", output);
+        let passports = fixtures::generate(fixture_options);
+        let written = File::create(output).and_then(|passports_file| {
+            let expected_file = File::create(&expected)?;
+            fixtures::write(
+                &passports,
+                BufWriter::new(passports_file),
+                BufWriter::new(expected_file),
+            )
+        });
+        match written {
+            Ok(()) => println!(
+                "Wrote {} passports, {} of them valid, to {} and their verdicts to {}",
+                passports.len(),
+                passports.iter().filter(|passport| passport.valid()).count(),
+                output,
+                expected
+            ),
+            Err(error) => println!("Could not write {}: {}", output, error),
+        }
+        return;
+    }
+
+    let input_file = options
+        .input_file
+        .as_deref()
+        .expect("an input file is required without --bench or --generate");
+
+    if let Some(format) = options.import {
+        let file = File::open(input_file).expect("no such file");
+        let stdout = io::stdout();
+        if let Err(error) = convert::import(BufReader::new(file), format, stdout.lock()) {
+            println!("Could not import {}: {}", input_file, error);
+        }
         return;
     }
 
-    let input_file = &args[1];
+    let as_of = options.as_of.unwrap_or_else(Date::today);
+
+    let rules = match &options.rules_file {
+        None => Ok(strictest(Ruleset::default(), options.strict)),
+        Some(rules_file) => load_rules(rules_file, as_of, options.strict),
+    };
+    let rules = match rules {
+        Ok(rules) => rules,
+        Err(message) => {
+            println!("{}", message);
+            return;
+        }
+    };
 
     let file = File::open(input_file).expect("no such file");
-    let buf = BufReader::new(file);
-    let lines: Vec<String> = buf
-        .lines()
-        .map(|l| l.expect("could not parse line"))
-        .collect();
 
-    let mut passports: Vec<Passport> = vec![];
-    let mut fields: HashMap<String, String> = HashMap::new();
+    if !options.compare.is_empty() {
+        let mut rulesets = vec![];
+        for (name, rules_file) in &options.compare {
+            let rules = match rules_file {
+                Some(rules_file) => load_rules(rules_file, as_of, options.strict),
+                None => Ruleset::named(name, as_of)
+                    .map(|rules| strictest(rules, options.strict))
+                    .ok_or(format!("There's no built in ruleset called {}", name)),
+            };
+            match rules {
+                Ok(rules) => rulesets.push(NamedRuleset {
+                    name: name.clone(),
+                    rules,
+                }),
+                Err(message) => {
+                    println!("{}", message);
+                    return;
+                }
+            }
+        }
 
-    for line in lines {
-        if line.trim() == "" {
-            passports.push(Passport::new(&fields));
-            fields = HashMap::new();
-            continue;
+        match compare::compare(&rulesets, PassportReader::new(BufReader::new(file))) {
+            Ok(comparison) => print_comparison(&rulesets, &comparison),
+            Err(error) => println!("Could not read {}: {}", input_file, error),
         }
+        return;
+    }
 
-        let entries: Vec<&str> = line.split(" ").collect();
-        for entry in entries {
-            let parts: Vec<&str> = entry.split(":").collect();
-            fields.insert(parts[0].to_string(), parts[1].to_string());
+    if let Some(format) = options.export {
+        let records = PassportReader::new(BufReader::new(file));
+        let stdout = io::stdout();
+        let output = BufWriter::new(stdout.lock());
+        match convert::export(records, &rules, format, options.validity, output) {
+            // The export itself is on stdout, so this can't go there.
+            Ok(not_canonical) if not_canonical > 0 => eprintln!(
+                "{} passports aren't written with single spaces and one blank line between \
+                 passports, so --import won't give them back exactly as they were",
+                not_canonical
+            ),
+            Ok(_) => {}
+            Err(error) => println!("Could not export {}: {}", input_file, error),
         }
+        return;
     }
 
-    passports.push(Passport::new(&fields));
+    if let Some(threads) = options.threads {
+        let validator = PassportValidator::new(rules);
+        match validator.count_valid(BufReader::new(file), threads) {
+            Ok(counts) => println!(
+                "Found {} valid passports out of {} total according to our rules",
+                counts.valid, counts.total
+            ),
+            Err(error) => println!("Could not read {}: {}", input_file, error),
+        }
+        return;
+    }
 
-    let total_passports = passports.len();
+    let reader = PassportReader::new(BufReader::new(file));
 
-    let valid_passports = passports
-        .iter()
-        .filter(|&passport| passport.valid())
-        .count();
+    let mut total_passports = 0;
+    let mut valid_passports = 0;
+    let mut validated: Vec<ValidatedPassport> = vec![];
+    let mut detector = options.anomalies.map(AnomalyDetector::new);
+
+    for record in reader {
+        let record = match record {
+            Ok(record) => record,
+            Err(error) => {
+                println!("Could not read {}: {}", input_file, error);
+                return;
+            }
+        };
+
+        total_passports += 1;
+        if record.passport.valid(&rules) {
+            valid_passports += 1;
+        }
+
+        if options.report {
+            report(total_passports, &record, &rules, options.suggest);
+        }
+
+        if let Some(detector) = &mut detector {
+            detector.add(total_passports, &record.passport);
+        }
+
+        if options.typed {
+            if let Ok(passport) = ValidatedPassport::try_from(record.passport) {
+                validated.push(passport);
+            }
+        }
+    }
+
+    if options.report {
+        println!();
+    }
+
+    if options.typed {
+        for passport in &validated {
+            println!("{}", passport);
+        }
+
+        let by_height = |a: &&Height, b: &&Height| a.centimeters().total_cmp(&b.centimeters());
+        let heights = validated.iter().map(|passport| &passport.hgt);
+        if let (Some(shortest), Some(tallest)) =
+            (heights.clone().min_by(by_height), heights.max_by(by_height))
+        {
+            println!(
+                "Heights range from {:.1}cm ({:.1}in) to {:.1}cm ({:.1}in)",
+                shortest.centimeters(),
+                shortest.inches(),
+                tallest.centimeters(),
+                tallest.inches()
+            );
+        }
+        println!();
+    }
+
+    if let Some(detector) = detector {
+        let anomalies = detector.finish();
+        for anomaly in &anomalies {
+            println!("{}", anomaly);
+        }
+        if !anomalies.is_empty() {
+            println!();
+        }
+    }
 
     println!(
         "Found {} valid passports out of {} total according to our rules",
         valid_passports, total_passports
     );
 }
+
+// Lists everything wrong with a passport, if there's anything to list.
+// Problems the rules allow are listed as warnings, and suggested fixes are
+// listed after everything else if asked for.
+fn report(number: usize, record: &Record, rules: &Ruleset, suggest: bool) {
+    let problems = record.passport.check(rules);
+    if problems.is_empty() && record.malformed.is_empty() {
+        return;
+    }
+
+    let invalid = problems
+        .iter()
+        .any(|problem| problem.invalidates(&rules.strictness));
+    println!(
+        "Passport {} (lines {}-{}) {}:",
+        number,
+        record.first_line,
+        record.last_line,
+        if invalid {
+            "is invalid"
+        } else {
+            "has warnings"
+        }
+    );
+    for problem in problems {
+        if problem.invalidates(&rules.strictness) {
+            println!("  {}", problem);
+        } else {
+            println!("  warning: {}", problem);
+        }
+    }
+    for token in &record.malformed {
+        println!("  {}", token);
+    }
+    if suggest {
+        for suggestion in suggest::suggest(&record.passport, rules) {
+            println!("  suggestion: {}", suggestion);
+        }
+    }
+}
+
+fn load_rules(rules_file: &str, as_of: Date, strict: bool) -> Result<Ruleset, String> {
+    let rules_input = fs::read_to_string(rules_file).expect("no such rules file");
+    Ruleset::parse_as_of(&rules_input, as_of)
+        .map(|rules| strictest(rules, strict))
+        .map_err(|error| format!("Could not read the rules in {}: {}", rules_file, error))
+}
+
+// With --strict every problem with the fields makes a passport invalid,
+// whatever the ruleset says.
+fn strictest(mut rules: Ruleset, strict: bool) -> Ruleset {
+    if strict {
+        rules.strictness = Strictness::strict();
+    }
+    rules
+}
+
+fn print_comparison(rulesets: &[NamedRuleset], comparison: &Comparison) {
+    for change in &comparison.changes {
+        println!(
+            "Passport {} (lines {}-{}):",
+            change.number, change.first_line, change.last_line
+        );
+        for (ruleset, verdict) in rulesets.iter().zip(&change.verdicts) {
+            let status = if verdict.valid { "valid" } else { "invalid" };
+            println!("  {}: {}", ruleset.name, status);
+            for error in &verdict.errors {
+                println!("    {}", error);
+            }
+        }
+    }
+    if !comparison.changes.is_empty() {
+        println!();
+    }
+
+    for (ruleset, valid) in rulesets.iter().zip(&comparison.valid) {
+        println!(
+            "{}: {} valid passports out of {}",
+            ruleset.name, valid, comparison.total
+        );
+    }
+    for (i, ruleset) in rulesets.iter().enumerate().skip(1) {
+        let (became_valid, became_invalid) = comparison.transitions(0, i);
+        println!(
+            "{} -> {}: {} became valid, {} became invalid",
+            rulesets[0].name, ruleset.name, became_valid, became_invalid
+        );
+    }
+}
@@ -0,0 +1,249 @@
+use crate::convert::Format;
+use crate::date::Date;
+use crate::fixtures::{FixtureOptions, Violation};
+
+pub const USAGE: &str = concat!(
+    "Usage: cargo run <input-file> [--rules <ruleset-file> [--as-of <YYYY-MM-DD>]] [--strict]\n",
+    "                  [--report] [--suggest] [--typed] [--anomalies [--cluster-size <n>]]\n",
+    "       cargo run <input-file> --compare <name>[=<ruleset-file>]... [--as-of <YYYY-MM-DD>]\n",
+    "                  [--strict]\n",
+    "       cargo run --release -- <input-file> --threads <n>\n",
+    "                  [--rules <ruleset-file> [--as-of <YYYY-MM-DD>]] [--strict]\n",
+    "       cargo run <input-file> --export jsonl|csv [--validity]\n",
+    "                  [--rules <ruleset-file> [--as-of <YYYY-MM-DD>]] [--strict]\n",
+    "       cargo run <jsonl-or-csv-file> --import jsonl|csv\n",
+    "       cargo run --release -- --bench <passports>\n",
+    "       cargo run -- --generate <passports> --output <file> [--seed <n>] [--valid-ratio <r>]\n",
+    "                  [--field-order fixed|shuffled] [--wrap <fields>|random]\n",
+    "                  [--violate <rule>]... [--cid-ratio <r>]",
+);
+
+// How many passports have to share a birth year and hair color before
+// --anomalies reports them.
+const DEFAULT_CLUSTER_SIZE: usize = 3;
+
+pub struct Options {
+    pub input_file: Option<String>,
+    pub rules_file: Option<String>,
+    pub as_of: Option<Date>,
+    pub strict: bool,
+    pub report: bool,
+    pub suggest: bool,
+    pub typed: bool,
+    pub anomalies: Option<usize>,
+    pub threads: Option<usize>,
+    pub compare: Vec<(String, Option<String>)>,
+    pub export: Option<Format>,
+    pub validity: bool,
+    pub import: Option<Format>,
+    pub bench: Option<usize>,
+    pub generate: Option<FixtureOptions>,
+    pub output: Option<String>,
+}
+
+impl Options {
+    pub fn parse(args: &[String]) -> Result<Options, String> {
+        let mut input_file = None;
+        let mut rules_file = None;
+        let mut as_of = None;
+        let mut strict = false;
+        let mut report = false;
+        let mut suggest = false;
+        let mut typed = false;
+        let mut anomalies = false;
+        let mut cluster_size = None;
+        let mut threads = None;
+        let mut compare = vec![];
+        let mut export = None;
+        let mut validity = false;
+        let mut import = None;
+        let mut bench = None;
+        let mut generate = None;
+        let mut output = None;
+        let mut fixtures = FixtureOptions::default();
+        let mut violations: Vec<Violation> = vec![];
+        let mut fixture_options = false;
+
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--rules" => rules_file = Some(value(&mut args, arg)?.to_string()),
+                "--as-of" => as_of = Some(value(&mut args, arg)?.parse()?),
+                "--strict" => strict = true,
+                "--report" => report = true,
+                "--suggest" => suggest = true,
+                "--typed" => typed = true,
+                "--anomalies" => anomalies = true,
+                "--cluster-size" => {
+                    cluster_size = Some(
+                        value(&mut args, arg)?
+                            .parse::<usize>()
+                            .ok()
+                            .filter(|&size| size > 1)
+                            .ok_or("--cluster-size must be at least 2")?,
+                    );
+                }
+                "--threads" => {
+                    threads = Some(
+                        value(&mut args, arg)?
+                            .parse::<usize>()
+                            .ok()
+                            .filter(|&threads| threads > 0)
+                            .ok_or("--threads must be a positive number")?,
+                    );
+                }
+                "--compare" => compare.push(named_ruleset(value(&mut args, arg)?)?),
+                "--export" => export = Some(value(&mut args, arg)?.parse()?),
+                "--validity" => validity = true,
+                "--import" => import = Some(value(&mut args, arg)?.parse()?),
+                "--bench" => {
+                    bench = Some(
+                        value(&mut args, arg)?
+                            .parse::<usize>()
+                            .map_err(|_| "--bench needs a number of passports")?,
+                    );
+                }
+                "--generate" => {
+                    generate = Some(
+                        value(&mut args, arg)?
+                            .parse::<usize>()
+                            .map_err(|_| "--generate needs a number of passports")?,
+                    );
+                }
+                "--output" => output = Some(value(&mut args, arg)?.to_string()),
+                "--seed" => {
+                    fixtures.seed = value(&mut args, arg)?
+                        .parse()
+                        .map_err(|_| "--seed must be a number")?;
+                    fixture_options = true;
+                }
+                "--valid-ratio" => {
+                    fixtures.valid_ratio = ratio(value(&mut args, arg)?, arg)?;
+                    fixture_options = true;
+                }
+                "--field-order" => {
+                    fixtures.field_order = value(&mut args, arg)?.parse()?;
+                    fixture_options = true;
+                }
+                "--wrap" => {
+                    fixtures.wrap = value(&mut args, arg)?.parse()?;
+                    fixture_options = true;
+                }
+                "--violate" => {
+                    violations.push(value(&mut args, arg)?.parse()?);
+                    fixture_options = true;
+                }
+                "--cid-ratio" => {
+                    fixtures.cid_ratio = ratio(value(&mut args, arg)?, arg)?;
+                    fixture_options = true;
+                }
+                flag if flag.starts_with("--") => return Err(format!("unknown option {}", flag)),
+                file => {
+                    if input_file.is_some() {
+                        return Err(format!("unexpected argument {}", file));
+                    }
+                    input_file = Some(file.to_string());
+                }
+            }
+        }
+
+        if generate.is_some() {
+            if input_file.is_some() {
+                return Err("--generate writes to --output rather than reading a file".to_string());
+            }
+            if output.is_none() {
+                return Err("--generate needs an --output file".to_string());
+            }
+        } else if fixture_options || output.is_some() {
+            return Err(concat!(
+                "--output, --seed, --valid-ratio, --field-order, --wrap, --violate and ",
+                "--cid-ratio only go with --generate"
+            )
+            .to_string());
+        }
+        let generate = generate.map(|passports| {
+            fixtures.passports = passports;
+            if !violations.is_empty() {
+                fixtures.violations = violations;
+            }
+            fixtures
+        });
+
+        if input_file.is_none() && bench.is_none() && generate.is_none() {
+            return Err("missing input file".to_string());
+        }
+        let modes = [
+            report || suggest || typed || anomalies,
+            threads.is_some(),
+            !compare.is_empty(),
+            export.is_some(),
+            import.is_some(),
+        ];
+        if modes.iter().filter(|&&mode| mode).count() > 1 {
+            return Err(concat!(
+                "only one of --report, --suggest, --typed or --anomalies, --threads, --compare, ",
+                "--export and --import can be used at once"
+            )
+            .to_string());
+        }
+        // The default rules only use fixed years, so the date would be ignored.
+        if as_of.is_some() && rules_file.is_none() && compare.is_empty() {
+            return Err("--as-of only goes with --rules or --compare".to_string());
+        }
+        if validity && export.is_none() {
+            return Err("--validity only goes with --export".to_string());
+        }
+        if cluster_size.is_some() && !anomalies {
+            return Err("--cluster-size only goes with --anomalies".to_string());
+        }
+
+        Ok(Options {
+            input_file,
+            rules_file,
+            as_of,
+            strict,
+            report: report || suggest,
+            suggest,
+            typed,
+            anomalies: if anomalies {
+                Some(cluster_size.unwrap_or(DEFAULT_CLUSTER_SIZE))
+            } else {
+                None
+            },
+            threads,
+            compare,
+            export,
+            validity,
+            import,
+            bench,
+            generate,
+            output,
+        })
+    }
+}
+
+fn value<'a>(args: &mut std::slice::Iter<'a, String>, flag: &str) -> Result<&'a str, String> {
+    args.next()
+        .map(|value| value.as_str())
+        .ok_or(format!("{} needs a value", flag))
+}
+
+fn ratio(value: &str, flag: &str) -> Result<f64, String> {
+    value
+        .parse::<f64>()
+        .ok()
+        .filter(|ratio| (0.0..=1.0).contains(ratio))
+        .ok_or(format!("{} must be between 0 and 1", flag))
+}
+
+// A ruleset for --compare: a name for a built in ruleset, or name=file.
+fn named_ruleset(value: &str) -> Result<(String, Option<String>), String> {
+    let (name, file) = match value.split_once('=') {
+        Some((name, file)) => (name, Some(file.to_string())),
+        None => (value, None),
+    };
+    if name.is_empty() {
+        return Err(format!("--compare {} needs a name", value));
+    }
+    Ok((name.to_string(), file))
+}
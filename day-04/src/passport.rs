@@ -1,107 +1,303 @@
-use regex::Regex;
-use std::collections::HashMap;
+use std::fmt;
 
-type Validator = fn(String) -> bool;
+use crate::rules::{Ruleset, Strictness};
 
+// Every field the passport was given, in order, including any repeats.
 pub struct Passport {
-    fields: HashMap<String, String>,
-    validators: HashMap<String, Validator>,
+    fields: Vec<(String, String)>,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum ValidationError {
+    Missing {
+        field: String,
+    },
+    Invalid {
+        field: String,
+        value: String,
+        reason: String,
+        rule: String,
+    },
+    Duplicate {
+        field: String,
+        value: String,
+        count: usize,
+    },
+    Conflicting {
+        field: String,
+        values: Vec<String>,
+    },
+    Unknown {
+        field: String,
+    },
+}
+
+impl ValidationError {
+    // Whether the error makes a passport invalid. Missing fields and invalid
+    // values always do, and the rest depend on how strict the rules are.
+    pub fn invalidates(&self, strictness: &Strictness) -> bool {
+        match self {
+            ValidationError::Missing { .. } | ValidationError::Invalid { .. } => true,
+            ValidationError::Duplicate { .. } => strictness.duplicates,
+            ValidationError::Conflicting { .. } => strictness.conflicts,
+            ValidationError::Unknown { .. } => strictness.unknown,
+        }
+    }
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ValidationError::Missing { field } => write!(f, "{}: missing required field", field),
+            ValidationError::Invalid {
+                field,
+                value,
+                reason,
+                rule,
+            } => write!(f, "{}={}: {} (rule: {})", field, value, reason, rule),
+            ValidationError::Duplicate {
+                field,
+                value,
+                count,
+            } => write!(f, "{}={}: given {} times", field, value, count),
+            ValidationError::Conflicting { field, values } => {
+                write!(
+                    f,
+                    "{}: given conflicting values {}",
+                    field,
+                    values.join(", ")
+                )
+            }
+            ValidationError::Unknown { field } => write!(f, "{}: not a known field", field),
+        }
+    }
 }
 
 impl Passport {
-    pub fn new(fields: &HashMap<String, String>) -> Passport {
-        let mut validators: HashMap<String, Validator> = HashMap::new();
-
-        validators.insert("byr".to_string(), validate_byr);
-        validators.insert("iyr".to_string(), validate_iyr);
-        validators.insert("eyr".to_string(), validate_eyr);
-        validators.insert("hgt".to_string(), validate_hgt);
-        validators.insert("hcl".to_string(), validate_hcl);
-        validators.insert("ecl".to_string(), validate_ecl);
-        validators.insert("pid".to_string(), validate_pid);
-
-        return Passport {
-            fields: fields.clone(),
-            validators,
-        };
+    pub fn new(fields: Vec<(String, String)>) -> Passport {
+        Passport { fields }
     }
 
-    pub fn valid(&self) -> bool {
-        return self
-            .validators
+    // A passport with the given fields, for tests to write out by hand.
+    #[cfg(test)]
+    pub fn from_pairs(fields: &[(&str, &str)]) -> Passport {
+        Passport::new(
+            fields
+                .iter()
+                .map(|(key, value)| (key.to_string(), value.to_string()))
+                .collect(),
+        )
+    }
+
+    // Every field in the order it was given.
+    pub fn fields(&self) -> &[(String, String)] {
+        &self.fields
+    }
+
+    // The value of a field. If it was given more than once, the last value
+    // wins.
+    pub fn get(&self, field: &str) -> Option<&str> {
+        self.fields
             .iter()
-            .all(|(field, validate)| match self.fields.get(field) {
-                None => false,
-                Some(val) => validate(val.to_string()),
-            });
+            .rev()
+            .find(|(name, _)| name == field)
+            .map(|(_, value)| value.as_str())
+    }
+
+    // Every value given for a field, in order.
+    pub fn get_all<'a>(&'a self, field: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+        self.fields
+            .iter()
+            .filter(move |(name, _)| name == field)
+            .map(|(_, value)| value.as_str())
     }
-}
 
-fn parse_year(val: String) -> Option<usize> {
-    if val.len() != 4 {
-        return None;
+    pub fn valid(&self, rules: &Ruleset) -> bool {
+        self.validate(rules).is_empty()
     }
 
-    let parse_result = val.parse::<usize>();
-    if parse_result.is_err() {
-        return None;
+    // Everything that makes the passport invalid under the given rules.
+    pub fn validate(&self, rules: &Ruleset) -> Vec<ValidationError> {
+        self.check(rules)
+            .into_iter()
+            .filter(|error| error.invalidates(&rules.strictness))
+            .collect()
     }
-    let year = parse_result.unwrap();
-    return Some(year);
-}
 
-fn validate_year_range(val: String, min: usize, max: usize) -> bool {
-    match parse_year(val) {
-        None => {
-            return false;
+    // Every problem with the passport, whether or not the rules are strict
+    // enough for it to make the passport invalid. Problems with values come
+    // first, in the order the ruleset lists fields, followed by repeated
+    // fields and then unknown ones in the order they first appear.
+    pub fn check(&self, rules: &Ruleset) -> Vec<ValidationError> {
+        let mut errors = vec![];
+
+        for rule in &rules.fields {
+            match (self.get(&rule.name), &rule.validator) {
+                (None, _) if rule.required => errors.push(ValidationError::Missing {
+                    field: rule.name.clone(),
+                }),
+                (Some(value), Some(validator)) => {
+                    if let Err(reason) = validator.validate(value) {
+                        errors.push(ValidationError::Invalid {
+                            field: rule.name.clone(),
+                            value: value.to_string(),
+                            reason,
+                            rule: validator.to_string(),
+                        });
+                    }
+                }
+                _ => {}
+            }
         }
-        Some(year) => {
-            if year < min || year > max {
-                return false;
+
+        let mut names: Vec<&str> = vec![];
+        for (name, _) in &self.fields {
+            if !names.contains(&name.as_str()) {
+                names.push(name);
             }
         }
-    }
-    return true;
-}
 
-fn validate_byr(val: String) -> bool {
-    return validate_year_range(val, 1920, 2020);
-}
+        for &name in &names {
+            let values: Vec<&str> = self.get_all(name).collect();
+            if values.len() == 1 {
+                continue;
+            }
 
-fn validate_iyr(val: String) -> bool {
-    return validate_year_range(val, 2010, 2020);
-}
+            let mut distinct: Vec<String> = vec![];
+            for &value in &values {
+                if !distinct.iter().any(|seen| seen == value) {
+                    distinct.push(value.to_string());
+                }
+            }
 
-fn validate_eyr(val: String) -> bool {
-    return validate_year_range(val, 2020, 2030);
-}
+            if distinct.len() == 1 {
+                errors.push(ValidationError::Duplicate {
+                    field: name.to_string(),
+                    value: distinct.remove(0),
+                    count: values.len(),
+                });
+            } else {
+                errors.push(ValidationError::Conflicting {
+                    field: name.to_string(),
+                    values: distinct,
+                });
+            }
+        }
 
-fn validate_hgt(val: String) -> bool {
-    let height_regex = Regex::new(r"^(\d+)(cm|in)$").unwrap();
-    return match height_regex.captures(&val) {
-        None => false,
-        Some(captures) => {
-            let value = captures.get(1).unwrap().as_str().parse::<usize>().unwrap();
-            let unit = captures.get(2).unwrap().as_str();
-            match unit {
-                "cm" => value >= 150 && value <= 293,
-                "in" => value >= 59 && value <= 76,
-                _ => false,
+        for &name in &names {
+            if !rules.fields.iter().any(|rule| rule.name == name) {
+                errors.push(ValidationError::Unknown {
+                    field: name.to_string(),
+                });
             }
         }
-    };
-}
 
-fn validate_hcl(val: String) -> bool {
-    let color_regex = Regex::new(r"^#[0-9a-f]{6}$").unwrap();
-    return color_regex.is_match(&val);
+        errors
+    }
 }
 
-fn validate_ecl(val: String) -> bool {
-    return vec!["amb", "blu", "brn", "gry", "grn", "hzl", "oth"].contains(&val.as_str());
-}
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-fn validate_pid(val: String) -> bool {
-    let pid_regex = Regex::new(r"^[0-9]{9}$").unwrap();
-    return pid_regex.is_match(&val);
+    #[test]
+    fn test_validate() {
+        let rules = Ruleset::default();
+        let passport = Passport::from_pairs(&[
+            ("byr", "1937"),
+            ("iyr", "2017"),
+            ("eyr", "2020"),
+            ("hgt", "190"),
+            ("hcl", "#fffffd"),
+            ("ecl", "gry"),
+        ]);
+
+        let errors = passport.validate(&rules);
+        assert_eq!(errors.len(), 2);
+        assert_eq!(
+            errors[0].to_string(),
+            "hgt=190: missing unit (rule: measurement 150-293cm or 59-76in)"
+        );
+        assert_eq!(
+            errors[1],
+            ValidationError::Missing {
+                field: "pid".to_string()
+            }
+        );
+        assert!(!passport.valid(&rules));
+    }
+
+    #[test]
+    fn test_valid() {
+        let rules = Ruleset::default();
+        let passport = Passport::from_pairs(&[
+            ("byr", "1937"),
+            ("iyr", "2017"),
+            ("eyr", "2020"),
+            ("hgt", "183cm"),
+            ("hcl", "#fffffd"),
+            ("ecl", "gry"),
+            ("pid", "860033327"),
+        ]);
+
+        assert!(passport.validate(&rules).is_empty());
+        assert!(passport.valid(&rules));
+    }
+
+    #[test]
+    fn test_repeated_and_unknown_fields() {
+        let mut rules = Ruleset::default();
+        let passport = Passport::from_pairs(&[
+            ("byr", "1937"),
+            ("iyr", "2017"),
+            ("eyr", "2020"),
+            ("hgt", "183cm"),
+            ("hcl", "#fffffd"),
+            ("ecl", "gry"),
+            ("pid", "860033327"),
+            ("hgt", "183cm"),
+            ("zzz", "1"),
+            ("ecl", "blu"),
+            ("ecl", "gry"),
+        ]);
+
+        assert_eq!(passport.get("ecl"), Some("gry"));
+        assert_eq!(
+            passport.get_all("ecl").collect::<Vec<_>>(),
+            ["gry", "blu", "gry"]
+        );
+        assert_eq!(
+            passport.check(&rules),
+            vec![
+                ValidationError::Duplicate {
+                    field: "hgt".to_string(),
+                    value: "183cm".to_string(),
+                    count: 2
+                },
+                ValidationError::Conflicting {
+                    field: "ecl".to_string(),
+                    values: vec!["gry".to_string(), "blu".to_string()]
+                },
+                ValidationError::Unknown {
+                    field: "zzz".to_string()
+                },
+            ]
+        );
+        // The last ecl is the one validated, so by default it's still valid.
+        assert!(passport.valid(&rules));
+
+        rules.strictness = Strictness {
+            duplicates: false,
+            conflicts: true,
+            unknown: false,
+        };
+        assert_eq!(
+            passport.validate(&rules)[0].to_string(),
+            "ecl: given conflicting values gry, blu"
+        );
+        assert_eq!(passport.validate(&rules).len(), 1);
+
+        rules.strictness = Strictness::strict();
+        assert_eq!(passport.validate(&rules).len(), 3);
+    }
 }
@@ -0,0 +1,253 @@
+use std::fmt;
+use std::io;
+use std::io::BufRead;
+
+use crate::passport::Passport;
+
+// A token that isn't a key:value pair. It's left out of the passport it was
+// found in, and reading carries on. field is how many of the passport's fields
+// came before it.
+#[derive(Debug, PartialEq)]
+pub struct MalformedToken {
+    pub line: usize,
+    pub field: usize,
+    pub token: String,
+    pub reason: &'static str,
+}
+
+impl fmt::Display for MalformedToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: '{}' {}", self.line, self.token, self.reason)
+    }
+}
+
+// One passport and the lines of the file it came from, counting from 1, along
+// with how many of its fields were on each of those lines. It's canonical if
+// it was written the way the puzzle writes passports: fields separated by
+// single spaces, lines ending in \n, and one blank line before the next
+// passport or none at the end of the file.
+pub struct Record {
+    pub passport: Passport,
+    pub first_line: usize,
+    pub last_line: usize,
+    pub fields_per_line: Vec<usize>,
+    pub malformed: Vec<MalformedToken>,
+    pub canonical: bool,
+}
+
+// Reads passports one at a time from anything buffered, so a file never has
+// to be loaded all at once. Fields are separated by any run of spaces or tabs,
+// lines can end in either \n or \r\n, and any number of blank lines can sit
+// between passports.
+pub struct PassportReader<R> {
+    input: R,
+    line: String,
+    line_number: usize,
+    done: bool,
+}
+
+impl<R: BufRead> PassportReader<R> {
+    pub fn new(input: R) -> PassportReader<R> {
+        PassportReader {
+            input,
+            line: String::new(),
+            line_number: 0,
+            done: false,
+        }
+    }
+
+    // Reads the next line into self.line, or returns false at the end of the
+    // input.
+    fn next_line(&mut self) -> io::Result<bool> {
+        self.line.clear();
+        if self.input.read_line(&mut self.line)? == 0 {
+            return Ok(false);
+        }
+        self.line_number += 1;
+        Ok(true)
+    }
+
+    // Whether the input ends here or the next line looks blank, without
+    // reading it. Either way, the blank line just read isn't a lone one between
+    // two passports.
+    fn blank_line_next(&mut self) -> bool {
+        match self.input.fill_buf() {
+            Ok(buffer) => buffer.first().is_none_or(|byte| b"\r\n \t".contains(byte)),
+            Err(_) => false,
+        }
+    }
+}
+
+fn strip_line_ending(line: &str) -> &str {
+    let line = line.strip_suffix('\n').unwrap_or(line);
+    line.strip_suffix('\r').unwrap_or(line)
+}
+
+impl<R: BufRead> Iterator for PassportReader<R> {
+    type Item = io::Result<Record>;
+
+    fn next(&mut self) -> Option<io::Result<Record>> {
+        if self.done {
+            return None;
+        }
+
+        let mut fields = vec![];
+        let mut fields_per_line = vec![];
+        let mut malformed = vec![];
+        let mut first_line = None;
+        let mut canonical = true;
+        // Blank lines after a passport count against it, so only the ones at
+        // the start of the input count against the one after them.
+        let at_start = self.line_number == 0;
+
+        loop {
+            match self.next_line() {
+                Ok(true) => {}
+                Ok(false) => {
+                    self.done = true;
+                    break;
+                }
+                Err(error) => {
+                    self.done = true;
+                    return Some(Err(error));
+                }
+            }
+
+            let line = strip_line_ending(&self.line);
+
+            if line.trim().is_empty() {
+                if first_line.is_some() {
+                    canonical &= self.line == "\n" && !self.blank_line_next();
+                    break;
+                }
+                canonical &= !at_start;
+                continue;
+            }
+
+            let line_number = self.line_number;
+            first_line.get_or_insert(line_number);
+            let fields_before = fields.len();
+            canonical &= !self.line.ends_with("\r\n")
+                && self.line.ends_with('\n')
+                && line.split(' ').eq(line.split_whitespace());
+
+            for token in line.split_whitespace() {
+                match token.split_once(':') {
+                    None => malformed.push(MalformedToken {
+                        line: line_number,
+                        field: fields.len(),
+                        token: token.to_string(),
+                        reason: "has no ':' between a field and its value",
+                    }),
+                    Some(("", _)) => malformed.push(MalformedToken {
+                        line: line_number,
+                        field: fields.len(),
+                        token: token.to_string(),
+                        reason: "has no field name",
+                    }),
+                    Some((key, value)) => {
+                        fields.push((key.to_string(), value.to_string()));
+                    }
+                }
+            }
+
+            fields_per_line.push(fields.len() - fields_before);
+        }
+
+        // A blank line always follows the last passport's lines, except at the
+        // end of the input.
+        let first_line = first_line?;
+        let last_line = if self.done {
+            self.line_number
+        } else {
+            self.line_number - 1
+        };
+
+        Some(Ok(Record {
+            passport: Passport::new(fields),
+            first_line,
+            last_line,
+            fields_per_line,
+            malformed,
+            canonical,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read(input: &str) -> Vec<Record> {
+        PassportReader::new(input.as_bytes())
+            .map(|record| record.unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_records_and_spans() {
+        let records = read("byr:1937 iyr:2017\ncid:147\n\n\n\nhgt:183cm\n");
+
+        assert_eq!(records.len(), 2);
+        assert_eq!((records[0].first_line, records[0].last_line), (1, 2));
+        assert_eq!(records[0].fields_per_line, vec![2, 1]);
+        assert_eq!(records[0].passport.get("cid"), Some("147"));
+        assert_eq!((records[1].first_line, records[1].last_line), (6, 6));
+        assert_eq!(records[1].passport.get("hgt"), Some("183cm"));
+
+        // Only the second is followed by the single blank line, or none.
+        assert!(!records[0].canonical);
+        assert!(records[1].canonical);
+        assert!(
+            !read(
+                "hgt:183cm
+
+"
+            )[0]
+            .canonical
+        );
+        assert!(!read("hgt:183cm")[0].canonical);
+    }
+
+    #[test]
+    fn test_whitespace_and_line_endings() {
+        let records = read("\r\nbyr:1937\t\tiyr:2017   eyr:2020 \r\nhgt:183cm\r\n \t\r\npid:1");
+
+        assert_eq!(records.len(), 2);
+        assert_eq!((records[0].first_line, records[0].last_line), (2, 3));
+        assert_eq!(records[0].passport.get("iyr"), Some("2017"));
+        assert_eq!(records[0].passport.get("eyr"), Some("2020"));
+        assert_eq!(records[0].passport.get("hgt"), Some("183cm"));
+        assert_eq!((records[1].first_line, records[1].last_line), (5, 5));
+        assert_eq!(records[1].passport.get("pid"), Some("1"));
+        assert!(records.iter().all(|record| !record.canonical));
+    }
+
+    #[test]
+    fn test_malformed_tokens() {
+        let records = read("byr:1937 oops :2017\nhcl:#a:b\n\necl:brn\n");
+
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].malformed.len(), 2);
+        assert_eq!(
+            records[0].malformed[0].to_string(),
+            "line 1: 'oops' has no ':' between a field and its value"
+        );
+        assert_eq!(records[0].malformed[1].token, ":2017");
+        assert_eq!(records[0].malformed[1].field, 1);
+        assert_eq!(records[0].fields_per_line, vec![1, 1]);
+        assert!(
+            read(
+                "oops
+byr:1937
+"
+            )[0]
+            .fields_per_line
+                == vec![0, 1]
+        );
+        assert_eq!(records[0].passport.get("byr"), Some("1937"));
+        assert_eq!(records[0].passport.get("hcl"), Some("#a:b"));
+        assert!(records[1].malformed.is_empty());
+        assert!(records.iter().all(|record| record.canonical));
+    }
+}
@@ -0,0 +1,535 @@
+use regex::Regex;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::OnceLock;
+
+use crate::date::Date;
+
+// The 2020 puzzle's rules, which are used unless another ruleset is given.
+pub const DEFAULT_RULES: &str = include_str!("../rules/2020.toml");
+
+// The rules from part 1 of the puzzle, which only check that fields are there.
+pub const PART1_RULES: &str = include_str!("../rules/part1.toml");
+
+// The 2020 rules with the years worked out from the as-of date instead.
+pub const CURRENT_RULES: &str = include_str!("../rules/current.toml");
+
+#[derive(Deserialize)]
+struct RulesetConfig {
+    #[serde(default)]
+    strictness: Strictness,
+    field: Vec<FieldConfig>,
+}
+
+#[derive(Deserialize)]
+struct FieldConfig {
+    name: String,
+    #[serde(default = "required_by_default")]
+    required: bool,
+    validator: Option<ValidatorConfig>,
+}
+
+fn required_by_default() -> bool {
+    true
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+enum ValidatorConfig {
+    Year { min: usize, max: usize },
+    Regex { pattern: String },
+    OneOf { values: Vec<String> },
+    Measurement { units: BTreeMap<String, Range> },
+    Age { min: Option<u32>, max: Option<u32> },
+    RelativeYear { min: Option<i64>, max: Option<i64> },
+}
+
+#[derive(Deserialize, Clone, Copy)]
+pub struct Range {
+    pub min: usize,
+    pub max: usize,
+}
+
+impl Range {
+    fn contains(&self, value: usize) -> bool {
+        (self.min..=self.max).contains(&value)
+    }
+}
+
+pub enum Validator {
+    Year(Range),
+    Regex(Regex),
+    OneOf(Vec<String>),
+    Measurement(BTreeMap<String, Range>),
+    // A year range worked out from the as-of date when the rules were loaded,
+    // along with the rule as it was written.
+    RelativeYear {
+        range: Range,
+        rule: String,
+        as_of: Date,
+    },
+}
+
+fn validate_year(range: &Range, value: &str) -> Result<(), String> {
+    if value.len() != 4 || !value.chars().all(|c| c.is_ascii_digit()) {
+        return Err("not a four digit year".to_string());
+    }
+    let year = value.parse::<usize>().expect("four digits parse");
+    if year < range.min {
+        return Err(format!("before {}", range.min));
+    }
+    if year > range.max {
+        return Err(format!("after {}", range.max));
+    }
+    Ok(())
+}
+
+// The range of four digit years from as-of year + min to as-of year + max,
+// where a missing bound is as far as four digits go.
+fn relative_range(as_of: Date, min: Option<i64>, max: Option<i64>) -> Range {
+    let year = |offset: i64| (as_of.year as i64 + offset).clamp(0, 9999) as usize;
+    Range {
+        min: min.map_or(0, year),
+        max: max.map_or(9999, year),
+    }
+}
+
+// A range that ends before it starts would make every value invalid, which
+// is never what was meant.
+fn check_bounds<T>(name: &str, min: Option<T>, max: Option<T>) -> Result<(), RulesetError>
+where
+    T: PartialOrd + fmt::Display,
+{
+    match (min, max) {
+        (Some(min), Some(max)) if min > max => Err(RulesetError(format!(
+            "the range for {} has min {} greater than max {}",
+            name, min, max
+        ))),
+        _ => Ok(()),
+    }
+}
+
+// How the relative rules were written, for reports.
+fn describe_age(min: Option<u32>, max: Option<u32>) -> String {
+    match (min, max) {
+        (Some(min), Some(max)) => format!("age {}-{}", min, max),
+        (Some(min), None) => format!("age at least {}", min),
+        (None, Some(max)) => format!("age at most {}", max),
+        (None, None) => "any age".to_string(),
+    }
+}
+
+fn describe_relative_year(min: Option<i64>, max: Option<i64>) -> String {
+    let year = |offset: i64| match offset {
+        0 => "the as-of year".to_string(),
+        1 => "1 year after the as-of year".to_string(),
+        -1 => "1 year before the as-of year".to_string(),
+        offset if offset > 0 => format!("{} years after the as-of year", offset),
+        offset => format!("{} years before the as-of year", -offset),
+    };
+    match (min, max) {
+        (Some(min), Some(max)) => format!("from {} to {}", year(min), year(max)),
+        (Some(min), None) => format!("no earlier than {}", year(min)),
+        (None, Some(max)) => format!("no later than {}", year(max)),
+        (None, None) => "any year".to_string(),
+    }
+}
+
+impl Validator {
+    // Ok if the value passes, otherwise the reason it doesn't.
+    pub fn validate(&self, value: &str) -> Result<(), String> {
+        match self {
+            Validator::Year(range) | Validator::RelativeYear { range, .. } => {
+                validate_year(range, value)
+            }
+            Validator::Regex(regex) => {
+                if regex.is_match(value) {
+                    Ok(())
+                } else {
+                    Err("does not match the pattern".to_string())
+                }
+            }
+            Validator::OneOf(values) => {
+                if values.iter().any(|allowed| allowed == value) {
+                    Ok(())
+                } else {
+                    Err("not an allowed value".to_string())
+                }
+            }
+            Validator::Measurement(units) => {
+                let digits = value.chars().take_while(|c| c.is_ascii_digit()).count();
+                let (number, unit) = value.split_at(digits);
+
+                if number.is_empty() {
+                    return Err("missing number".to_string());
+                }
+                if unit.is_empty() {
+                    return Err("missing unit".to_string());
+                }
+
+                let range = units.get(unit).ok_or(format!("unknown unit {}", unit))?;
+                let number = number.parse::<usize>().map_err(|_| "number too large")?;
+
+                if range.contains(number) {
+                    Ok(())
+                } else {
+                    Err(format!("outside {}-{}{}", range.min, range.max, unit))
+                }
+            }
+        }
+    }
+}
+
+// Describes the rule itself, for reports about values that broke it.
+impl fmt::Display for Validator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Validator::Year(range) => write!(f, "year {}-{}", range.min, range.max),
+            Validator::Regex(regex) => write!(f, "regex {}", regex.as_str()),
+            Validator::OneOf(values) => write!(f, "one of {}", values.join(" ")),
+            Validator::Measurement(units) => {
+                let units: Vec<String> = units
+                    .iter()
+                    .map(|(unit, range)| format!("{}-{}{}", range.min, range.max, unit))
+                    .collect();
+                write!(f, "measurement {}", units.join(" or "))
+            }
+            Validator::RelativeYear { range, rule, as_of } => write!(
+                f,
+                "{}; as of {} that's {}-{}",
+                rule, as_of, range.min, range.max
+            ),
+        }
+    }
+}
+
+pub struct FieldRule {
+    pub name: String,
+    pub required: bool,
+    pub validator: Option<Validator>,
+}
+
+// Which problems with the fields themselves, rather than their values, make
+// a passport invalid. By default none of them do, and a field given different
+// values is validated by the last one, as it always was.
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct Strictness {
+    pub duplicates: bool,
+    pub conflicts: bool,
+    pub unknown: bool,
+}
+
+impl Strictness {
+    pub fn strict() -> Strictness {
+        Strictness {
+            duplicates: true,
+            conflicts: true,
+            unknown: true,
+        }
+    }
+}
+
+pub struct Ruleset {
+    pub fields: Vec<FieldRule>,
+    pub strictness: Strictness,
+}
+
+#[derive(Debug)]
+pub struct RulesetError(String);
+
+impl fmt::Display for RulesetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl Ruleset {
+    // Read a ruleset from TOML, with any relative years worked out from
+    // today's date.
+    pub fn parse(input: &str) -> Result<Ruleset, RulesetError> {
+        Ruleset::parse_as_of(input, Date::today())
+    }
+
+    // Read a ruleset from TOML, compiling any regexes up front so a bad
+    // pattern is reported when the rules are loaded, as is a range that ends
+    // before it starts, and working out the years for any rules relative to
+    // the as-of date.
+    pub fn parse_as_of(input: &str, as_of: Date) -> Result<Ruleset, RulesetError> {
+        let config: RulesetConfig =
+            toml::from_str(input).map_err(|error| RulesetError(error.to_string()))?;
+
+        let mut fields = vec![];
+        for field in config.field {
+            if fields
+                .iter()
+                .any(|rule: &FieldRule| rule.name == field.name)
+            {
+                return Err(RulesetError(format!(
+                    "the {} field is listed more than once",
+                    field.name
+                )));
+            }
+
+            match &field.validator {
+                Some(ValidatorConfig::Year { min, max }) => {
+                    check_bounds(&field.name, Some(min), Some(max))?
+                }
+                Some(ValidatorConfig::Measurement { units }) => {
+                    for (unit, range) in units {
+                        let name = format!("{} in {}", field.name, unit);
+                        check_bounds(&name, Some(range.min), Some(range.max))?;
+                    }
+                }
+                Some(ValidatorConfig::Age { min, max }) => check_bounds(&field.name, *min, *max)?,
+                Some(ValidatorConfig::RelativeYear { min, max }) => {
+                    check_bounds(&field.name, *min, *max)?
+                }
+                _ => {}
+            }
+
+            let name = field.name;
+            let validator = match field.validator {
+                None => None,
+                Some(ValidatorConfig::Year { min, max }) => {
+                    Some(Validator::Year(Range { min, max }))
+                }
+                Some(ValidatorConfig::Regex { pattern }) => {
+                    let regex = Regex::new(&pattern).map_err(|error| {
+                        RulesetError(format!("bad pattern for {}: {}", name, error))
+                    })?;
+                    Some(Validator::Regex(regex))
+                }
+                Some(ValidatorConfig::OneOf { values }) => Some(Validator::OneOf(values)),
+                Some(ValidatorConfig::Measurement { units }) => Some(Validator::Measurement(units)),
+                Some(ValidatorConfig::Age { min, max }) => Some(Validator::RelativeYear {
+                    range: relative_range(
+                        as_of,
+                        max.map(|max| -(max as i64)),
+                        min.map(|min| -(min as i64)),
+                    ),
+                    rule: describe_age(min, max),
+                    as_of,
+                }),
+                Some(ValidatorConfig::RelativeYear { min, max }) => Some(Validator::RelativeYear {
+                    range: relative_range(as_of, min, max),
+                    rule: describe_relative_year(min, max),
+                    as_of,
+                }),
+            };
+
+            fields.push(FieldRule {
+                name,
+                required: field.required,
+                validator,
+            });
+        }
+
+        Ok(Ruleset {
+            fields,
+            strictness: config.strictness,
+        })
+    }
+
+    // One of the rulesets built in by name: part1, part2 for the default
+    // rules, or current for the default rules relative to the as-of date.
+    pub fn named(name: &str, as_of: Date) -> Option<Ruleset> {
+        let input = match name {
+            "part1" => PART1_RULES,
+            "part2" => DEFAULT_RULES,
+            "current" => CURRENT_RULES,
+            _ => return None,
+        };
+        Some(Ruleset::parse_as_of(input, as_of).expect("the built in rules are valid"))
+    }
+
+    // The default rules, parsed once and shared by everything that needs the
+    // 2020 rules in particular rather than whichever ruleset was loaded.
+    pub fn builtin() -> &'static Ruleset {
+        static BUILTIN: OnceLock<Ruleset> = OnceLock::new();
+        BUILTIN.get_or_init(Ruleset::default)
+    }
+}
+
+impl Default for Ruleset {
+    fn default() -> Ruleset {
+        Ruleset::parse(DEFAULT_RULES).expect("the default rules are valid")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn validator<'a>(rules: &'a Ruleset, name: &str) -> &'a Validator {
+        let rule = rules.fields.iter().find(|rule| rule.name == name).unwrap();
+        rule.validator.as_ref().unwrap()
+    }
+
+    #[test]
+    fn test_default_rules() {
+        let rules = Ruleset::default();
+
+        assert!(validator(&rules, "byr").validate("2020").is_ok());
+        assert!(validator(&rules, "byr").validate("2021").is_err());
+        assert!(validator(&rules, "byr").validate("02002").is_err());
+
+        assert!(validator(&rules, "hgt").validate("60in").is_ok());
+        assert!(validator(&rules, "hgt").validate("190cm").is_ok());
+        assert!(validator(&rules, "hgt").validate("190in").is_err());
+        assert!(validator(&rules, "hgt").validate("190").is_err());
+
+        assert!(validator(&rules, "hcl").validate("#123abc").is_ok());
+        assert!(validator(&rules, "hcl").validate("#123abz").is_err());
+        assert!(validator(&rules, "hcl").validate("123abc").is_err());
+
+        assert!(validator(&rules, "ecl").validate("brn").is_ok());
+        assert!(validator(&rules, "ecl").validate("wat").is_err());
+
+        assert!(validator(&rules, "pid").validate("000000001").is_ok());
+        assert!(validator(&rules, "pid").validate("0123456789").is_err());
+
+        assert_eq!(
+            validator(&rules, "hgt").validate("190"),
+            Err("missing unit".to_string())
+        );
+        assert_eq!(
+            validator(&rules, "hgt").validate("cm"),
+            Err("missing number".to_string())
+        );
+        assert_eq!(
+            validator(&rules, "hgt").validate("190ft"),
+            Err("unknown unit ft".to_string())
+        );
+        assert_eq!(
+            validator(&rules, "hgt").validate("80in"),
+            Err("outside 59-76in".to_string())
+        );
+        assert_eq!(
+            validator(&rules, "byr").validate("1919"),
+            Err("before 1920".to_string())
+        );
+        assert_eq!(
+            validator(&rules, "hgt").to_string(),
+            "measurement 150-293cm or 59-76in"
+        );
+
+        let cid = rules.fields.iter().find(|rule| rule.name == "cid").unwrap();
+        assert!(!cid.required);
+        assert!(cid.validator.is_none());
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(Ruleset::parse("[[field]]\nrequired = true\n").is_err());
+        assert!(Ruleset::parse(
+            "[[field]]\nname = \"pid\"\nvalidator = { kind = \"regex\", pattern = \"(\" }\n"
+        )
+        .is_err());
+        assert!(Ruleset::parse(
+            "[[field]]\nname = \"pid\"\nvalidator = { kind = \"palindrome\" }\n"
+        )
+        .is_err());
+        assert!(Ruleset::parse("[[field]]\nname = \"pid\"\n[[field]]\nname = \"pid\"\n").is_err());
+        assert!(
+            Ruleset::parse("[strictness]\nrepeats = true\n[[field]]\nname = \"pid\"\n").is_err()
+        );
+
+        let error = |validator: &str| {
+            Ruleset::parse(&format!(
+                "[[field]]\nname = \"byr\"\nvalidator = {}\n",
+                validator
+            ))
+            .err()
+            .map(|error| error.to_string())
+        };
+        assert_eq!(
+            error("{ kind = \"year\", min = 2002, max = 1920 }"),
+            Some("the range for byr has min 2002 greater than max 1920".to_string())
+        );
+        assert_eq!(
+            error("{ kind = \"measurement\", units = { cm = { min = 193, max = 150 } } }"),
+            Some("the range for byr in cm has min 193 greater than max 150".to_string())
+        );
+        assert_eq!(
+            error("{ kind = \"age\", min = 100, max = 0 }"),
+            Some("the range for byr has min 100 greater than max 0".to_string())
+        );
+        assert_eq!(
+            error("{ kind = \"relative_year\", min = 10, max = -10 }"),
+            Some("the range for byr has min 10 greater than max -10".to_string())
+        );
+        assert_eq!(error("{ kind = \"year\", min = 1920, max = 1920 }"), None);
+    }
+
+    #[test]
+    fn test_named() {
+        let as_of = "2020-12-04".parse().unwrap();
+        let part1 = Ruleset::named("part1", as_of).unwrap();
+        assert_eq!(part1.fields.len(), 8);
+        assert!(part1.fields.iter().all(|rule| rule.validator.is_none()));
+        assert!(!part1.strictness.conflicts);
+
+        assert_eq!(Ruleset::named("part2", as_of).unwrap().fields.len(), 8);
+        assert_eq!(Ruleset::named("current", as_of).unwrap().fields.len(), 8);
+        assert!(Ruleset::named("part3", as_of).is_none());
+    }
+
+    #[test]
+    fn test_relative_years() {
+        let rules = Ruleset::parse_as_of(
+            "[[field]]
+name = \"byr\"
+validator = { kind = \"age\", min = 0, max = 100 }
+[[field]]
+name = \"eyr\"
+validator = { kind = \"relative_year\", min = 0 }
+",
+            "2026-10-19".parse().unwrap(),
+        )
+        .unwrap();
+
+        assert!(validator(&rules, "byr").validate("1926").is_ok());
+        assert!(validator(&rules, "byr").validate("2026").is_ok());
+        assert_eq!(
+            validator(&rules, "byr").validate("1925"),
+            Err("before 1926".to_string())
+        );
+        assert!(validator(&rules, "byr").validate("2027").is_err());
+        assert_eq!(
+            validator(&rules, "byr").to_string(),
+            "age 0-100; as of 2026-10-19 that's 1926-2026"
+        );
+
+        assert!(validator(&rules, "eyr").validate("2026").is_ok());
+        assert!(validator(&rules, "eyr").validate("2025").is_err());
+        assert_eq!(
+            validator(&rules, "eyr").to_string(),
+            "no earlier than the as-of year; as of 2026-10-19 that's 2026-9999"
+        );
+
+        let current = Ruleset::named("current", "2020-12-04".parse().unwrap()).unwrap();
+        let default = Ruleset::default();
+        for name in &["byr", "iyr", "eyr"] {
+            for year in 1900..2040 {
+                let year = year.to_string();
+                assert_eq!(
+                    validator(&current, name).validate(&year).is_ok(),
+                    validator(&default, name).validate(&year).is_ok()
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_strictness() {
+        assert_eq!(Ruleset::default().strictness, Strictness::default());
+
+        let rules =
+            Ruleset::parse("[strictness]\nunknown = true\n[[field]]\nname = \"pid\"\n").unwrap();
+        assert!(rules.strictness.unknown);
+        assert!(!rules.strictness.conflicts);
+        assert!(!rules.strictness.duplicates);
+    }
+}
@@ -0,0 +1,226 @@
+use std::fmt;
+
+use crate::passport::{Passport, ValidationError};
+use crate::rules::{Ruleset, Validator};
+
+// A value that would probably pass in place of one that didn't, along with how
+// it was arrived at. Suggestions are only ever reported; the passport is left
+// as it was.
+#[derive(Debug, PartialEq)]
+pub struct Suggestion {
+    pub field: String,
+    pub value: String,
+    pub suggested: String,
+    pub reason: String,
+}
+
+impl fmt::Display for Suggestion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}={}: did you mean {}:{}? ({})",
+            self.field, self.value, self.field, self.suggested, self.reason
+        )
+    }
+}
+
+// A suggestion for every invalid value in the passport that there's a likely
+// fix for, in the order the ruleset lists fields.
+pub fn suggest(passport: &Passport, rules: &Ruleset) -> Vec<Suggestion> {
+    passport
+        .check(rules)
+        .into_iter()
+        .filter_map(|error| match error {
+            ValidationError::Invalid { field, value, .. } => {
+                let rule = rules.fields.iter().find(|rule| rule.name == field)?;
+                let (suggested, reason) = suggest_value(rule.validator.as_ref()?, &value)?;
+                Some(Suggestion {
+                    field,
+                    value,
+                    suggested,
+                    reason,
+                })
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+// The likely intended value for one that the validator rejected, if there's
+// exactly one that passes.
+fn suggest_value(validator: &Validator, value: &str) -> Option<(String, String)> {
+    let candidates = match validator {
+        Validator::Year(_) | Validator::RelativeYear { .. } => vec![(
+            value.trim_start_matches('0').to_string(),
+            "removed leading zeros".to_string(),
+        )],
+        Validator::Measurement(units) => {
+            let digits = value.chars().take_while(|c| c.is_ascii_digit()).count();
+            let (number, unit) = value.split_at(digits);
+            if unit.is_empty() {
+                units
+                    .keys()
+                    .map(|unit| {
+                        (
+                            format!("{}{}", number, unit),
+                            format!("inferred the unit {} from the number", unit),
+                        )
+                    })
+                    .collect()
+            } else {
+                vec![(
+                    format!("{}{}", number, unit.trim().to_lowercase()),
+                    "normalized the unit".to_string(),
+                )]
+            }
+        }
+        Validator::OneOf(values) => {
+            let lowercase = value.to_lowercase();
+            if values.contains(&lowercase) {
+                vec![(lowercase, "normalized the case".to_string())]
+            } else {
+                closest(values, &lowercase)
+                    .into_iter()
+                    .map(|(allowed, edits)| {
+                        (
+                            allowed.to_string(),
+                            format!(
+                                "closest allowed value, {} edit{} away",
+                                edits,
+                                if edits == 1 { "" } else { "s" }
+                            ),
+                        )
+                    })
+                    .collect()
+            }
+        }
+        Validator::Regex(_) => {
+            let mut candidates = vec![
+                (value.to_lowercase(), "normalized the case".to_string()),
+                (value.to_uppercase(), "normalized the case".to_string()),
+                (format!("#{}", value), "added the missing #".to_string()),
+                (
+                    format!("#{}", value.to_lowercase()),
+                    "added the missing # and normalized the case".to_string(),
+                ),
+            ];
+            if value.chars().all(|c| c.is_ascii_digit()) {
+                for padding in 1..=3 {
+                    candidates.push((
+                        format!("{}{}", "0".repeat(padding), value),
+                        "padded with leading zeros".to_string(),
+                    ));
+                }
+            }
+            candidates
+        }
+    };
+
+    let mut passing: Vec<(String, String)> = vec![];
+    for (candidate, reason) in candidates {
+        if candidate != value
+            && validator.validate(&candidate).is_ok()
+            && !passing.iter().any(|(passed, _)| *passed == candidate)
+        {
+            passing.push((candidate, reason));
+        }
+    }
+
+    if passing.len() == 1 {
+        passing.pop()
+    } else {
+        None
+    }
+}
+
+// The allowed values nearest to the value, if any are close enough to be a
+// misspelling, which is one edit for every three characters in the allowed
+// value. More than one comes back when there's a tie.
+fn closest<'a>(values: &'a [String], value: &str) -> Vec<(&'a str, usize)> {
+    let distances: Vec<(&str, usize)> = values
+        .iter()
+        .map(|allowed| (allowed.as_str(), edit_distance(allowed, value)))
+        .filter(|&(allowed, edits)| edits <= (allowed.len() / 3).max(1))
+        .collect();
+
+    match distances.iter().map(|&(_, edits)| edits).min() {
+        None => vec![],
+        Some(min) => distances
+            .into_iter()
+            .filter(|&(_, edits)| edits == min)
+            .collect(),
+    }
+}
+
+// The Levenshtein distance: how many characters have to be inserted, deleted
+// or replaced to turn one string into the other.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+
+    for (i, a) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, &b) in b.iter().enumerate() {
+            let replace = previous[j] + if a == b { 0 } else { 1 };
+            current.push(replace.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+
+    previous[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn suggestions(fields: &[(&str, &str)]) -> Vec<(String, String)> {
+        suggest(&Passport::from_pairs(fields), &Ruleset::default())
+            .into_iter()
+            .map(|suggestion| (suggestion.field, suggestion.suggested))
+            .collect()
+    }
+
+    fn suggestion(field: &str, value: &str) -> Option<String> {
+        suggestions(&[(field, value)])
+            .pop()
+            .map(|(_, suggested)| suggested)
+    }
+
+    #[test]
+    fn test_suggestions() {
+        assert_eq!(suggestion("hgt", "190"), Some("190cm".to_string()));
+        assert_eq!(suggestion("hgt", "70"), Some("70in".to_string()));
+        assert_eq!(suggestion("hgt", "190CM"), Some("190cm".to_string()));
+        assert_eq!(suggestion("hgt", "100"), None);
+        assert_eq!(suggestion("ecl", "blue"), Some("blu".to_string()));
+        assert_eq!(suggestion("ecl", "HZL"), Some("hzl".to_string()));
+        assert_eq!(suggestion("ecl", "gr"), None);
+        assert_eq!(suggestion("ecl", "purple"), None);
+        assert_eq!(suggestion("hcl", "#ABCDEF"), Some("#abcdef".to_string()));
+        assert_eq!(suggestion("hcl", "abcdef"), Some("#abcdef".to_string()));
+        assert_eq!(suggestion("pid", "12345678"), Some("012345678".to_string()));
+        assert_eq!(suggestion("pid", "0123456789"), None);
+        assert_eq!(suggestion("byr", "01937"), Some("1937".to_string()));
+        assert_eq!(suggestion("byr", "1937"), None);
+    }
+
+    #[test]
+    fn test_only_invalid_values() {
+        assert_eq!(
+            suggestions(&[("hgt", "190"), ("ecl", "brn"), ("pid", "1234567")]),
+            vec![
+                ("hgt".to_string(), "190cm".to_string()),
+                ("pid".to_string(), "001234567".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn test_edit_distance() {
+        assert_eq!(edit_distance("blu", "blue"), 1);
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+        assert_eq!(edit_distance("", "abc"), 3);
+        assert_eq!(edit_distance("gry", "gry"), 0);
+    }
+}
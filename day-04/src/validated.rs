@@ -0,0 +1,333 @@
+use std::convert::TryFrom;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::passport::{Passport, ValidationError};
+use crate::rules::Ruleset;
+
+const CM_PER_INCH: f64 = 2.54;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Height {
+    Cm(u16),
+    In(u16),
+}
+
+impl Height {
+    pub fn centimeters(&self) -> f64 {
+        match *self {
+            Height::Cm(cm) => cm as f64,
+            Height::In(inches) => inches as f64 * CM_PER_INCH,
+        }
+    }
+
+    pub fn inches(&self) -> f64 {
+        match *self {
+            Height::Cm(cm) => cm as f64 / CM_PER_INCH,
+            Height::In(inches) => inches as f64,
+        }
+    }
+}
+
+impl FromStr for Height {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Height, String> {
+        let digits = value.chars().take_while(|c| c.is_ascii_digit()).count();
+        let (number, unit) = value.split_at(digits);
+        let number = number
+            .parse::<u16>()
+            .map_err(|_| "not a height".to_string())?;
+
+        match unit {
+            "cm" => Ok(Height::Cm(number)),
+            "in" => Ok(Height::In(number)),
+            _ => Err(format!("unknown unit {}", unit)),
+        }
+    }
+}
+
+impl fmt::Display for Height {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Height::Cm(cm) => write!(f, "{}cm", cm),
+            Height::In(inches) => write!(f, "{}in", inches),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum EyeColor {
+    Amber,
+    Blue,
+    Brown,
+    Gray,
+    Green,
+    Hazel,
+    Other,
+}
+
+impl FromStr for EyeColor {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<EyeColor, String> {
+        match value {
+            "amb" => Ok(EyeColor::Amber),
+            "blu" => Ok(EyeColor::Blue),
+            "brn" => Ok(EyeColor::Brown),
+            "gry" => Ok(EyeColor::Gray),
+            "grn" => Ok(EyeColor::Green),
+            "hzl" => Ok(EyeColor::Hazel),
+            "oth" => Ok(EyeColor::Other),
+            _ => Err("not an eye color".to_string()),
+        }
+    }
+}
+
+// Written back as the three letter code used in the passport files.
+impl fmt::Display for EyeColor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let code = match self {
+            EyeColor::Amber => "amb",
+            EyeColor::Blue => "blu",
+            EyeColor::Brown => "brn",
+            EyeColor::Gray => "gry",
+            EyeColor::Green => "grn",
+            EyeColor::Hazel => "hzl",
+            EyeColor::Other => "oth",
+        };
+        write!(f, "{}", code)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct HairColor {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+}
+
+impl FromStr for HairColor {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<HairColor, String> {
+        let hex = value
+            .strip_prefix('#')
+            .filter(|hex| hex.len() == 6 && hex.chars().all(|c| c.is_ascii_hexdigit()))
+            .ok_or("not a #rrggbb color")?;
+        let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).expect("two hex digits");
+
+        Ok(HairColor {
+            r: channel(0),
+            g: channel(2),
+            b: channel(4),
+        })
+    }
+}
+
+impl fmt::Display for HairColor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "#{:02x}{:02x}{:02x}", self.r, self.g, self.b)
+    }
+}
+
+// Passport ids are nine digits including any leading zeros, so the number is
+// padded back out to nine digits when it's written.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct PassportId(pub u32);
+
+impl FromStr for PassportId {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<PassportId, String> {
+        if value.len() != 9 || !value.chars().all(|c| c.is_ascii_digit()) {
+            return Err("not a nine digit id".to_string());
+        }
+        Ok(PassportId(value.parse().expect("nine digits parse")))
+    }
+}
+
+impl fmt::Display for PassportId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:09}", self.0)
+    }
+}
+
+// A passport that passed the 2020 rules, with each field parsed into its real
+// type.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ValidatedPassport {
+    pub byr: u16,
+    pub iyr: u16,
+    pub eyr: u16,
+    pub hgt: Height,
+    pub hcl: HairColor,
+    pub ecl: EyeColor,
+    pub pid: PassportId,
+    pub cid: Option<String>,
+}
+
+impl TryFrom<Passport> for ValidatedPassport {
+    type Error = Vec<ValidationError>;
+
+    // Fails with everything the 2020 rules find wrong with the passport. If a
+    // value gets past the rules but still can't be parsed, that's reported in
+    // the same way, with its type standing in for the rule.
+    fn try_from(passport: Passport) -> Result<ValidatedPassport, Vec<ValidationError>> {
+        let errors = passport.validate(Ruleset::builtin());
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+
+        let mut errors = vec![];
+        let byr = field(&passport, "byr", "u16", &mut errors);
+        let iyr = field(&passport, "iyr", "u16", &mut errors);
+        let eyr = field(&passport, "eyr", "u16", &mut errors);
+        let hgt = field(&passport, "hgt", "Height", &mut errors);
+        let hcl = field(&passport, "hcl", "HairColor", &mut errors);
+        let ecl = field(&passport, "ecl", "EyeColor", &mut errors);
+        let pid = field(&passport, "pid", "PassportId", &mut errors);
+
+        match (byr, iyr, eyr, hgt, hcl, ecl, pid) {
+            (Some(byr), Some(iyr), Some(eyr), Some(hgt), Some(hcl), Some(ecl), Some(pid)) => {
+                Ok(ValidatedPassport {
+                    byr,
+                    iyr,
+                    eyr,
+                    hgt,
+                    hcl,
+                    ecl,
+                    pid,
+                    cid: passport.get("cid").map(|cid| cid.to_string()),
+                })
+            }
+            _ => Err(errors),
+        }
+    }
+}
+
+fn field<T>(
+    passport: &Passport,
+    name: &str,
+    type_name: &str,
+    errors: &mut Vec<ValidationError>,
+) -> Option<T>
+where
+    T: FromStr,
+    T::Err: ToString,
+{
+    let value = match passport.get(name) {
+        Some(value) => value,
+        None => {
+            errors.push(ValidationError::Missing {
+                field: name.to_string(),
+            });
+            return None;
+        }
+    };
+
+    match value.parse() {
+        Ok(parsed) => Some(parsed),
+        Err(reason) => {
+            errors.push(ValidationError::Invalid {
+                field: name.to_string(),
+                value: value.to_string(),
+                reason: reason.to_string(),
+                rule: format!("type {}", type_name),
+            });
+            None
+        }
+    }
+}
+
+// Written out as a single line passport, in the same field order every time.
+impl fmt::Display for ValidatedPassport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "byr:{} iyr:{} eyr:{} hgt:{} hcl:{} ecl:{} pid:{}",
+            self.byr, self.iyr, self.eyr, self.hgt, self.hcl, self.ecl, self.pid
+        )?;
+        if let Some(cid) = &self.cid {
+            write!(f, " cid:{}", cid)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_try_from() {
+        let validated = ValidatedPassport::try_from(Passport::from_pairs(&[
+            ("pid", "087499704"),
+            ("hgt", "74in"),
+            ("ecl", "grn"),
+            ("iyr", "2012"),
+            ("eyr", "2030"),
+            ("byr", "1980"),
+            ("hcl", "#623a2f"),
+        ]))
+        .unwrap();
+
+        assert_eq!(validated.byr, 1980);
+        assert_eq!(validated.hgt, Height::In(74));
+        assert_eq!(validated.ecl, EyeColor::Green);
+        assert_eq!(
+            validated.hcl,
+            HairColor {
+                r: 0x62,
+                g: 0x3a,
+                b: 0x2f
+            }
+        );
+        assert_eq!(validated.pid, PassportId(87499704));
+        assert_eq!(validated.cid, None);
+        assert_eq!(
+            validated.to_string(),
+            "byr:1980 iyr:2012 eyr:2030 hgt:74in hcl:#623a2f ecl:grn pid:087499704"
+        );
+    }
+
+    #[test]
+    fn test_try_from_invalid() {
+        let errors = ValidatedPassport::try_from(Passport::from_pairs(&[
+            ("eyr", "1972"),
+            ("cid", "100"),
+            ("hcl", "#18171d"),
+            ("ecl", "amb"),
+            ("hgt", "170"),
+            ("pid", "186cm"),
+            ("iyr", "2018"),
+            ("byr", "1926"),
+        ]))
+        .unwrap_err();
+
+        assert_eq!(errors.len(), 3);
+        assert_eq!(
+            errors[0].to_string(),
+            "eyr=1972: before 2020 (rule: year 2020-2030)"
+        );
+    }
+
+    #[test]
+    fn test_height_conversion() {
+        assert_eq!(Height::In(10).centimeters(), 25.4);
+        assert_eq!(Height::Cm(254).inches(), 100.0);
+        assert_eq!(Height::Cm(150).centimeters(), 150.0);
+        assert_eq!("193cm".parse(), Ok(Height::Cm(193)));
+        assert_eq!(
+            "193ft".parse::<Height>(),
+            Err("unknown unit ft".to_string())
+        );
+    }
+
+    #[test]
+    fn test_passport_id_padding() {
+        assert_eq!("000000001".parse(), Ok(PassportId(1)));
+        assert_eq!(PassportId(1).to_string(), "000000001");
+        assert!("12345678".parse::<PassportId>().is_err());
+    }
+}
@@ -0,0 +1,265 @@
+use std::io;
+use std::io::BufRead;
+use std::sync::mpsc;
+use std::sync::Mutex;
+use std::thread;
+
+use crate::rules::Ruleset;
+
+// How many passports each thread is handed at a time.
+const BATCH_SIZE: usize = 1024;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Counts {
+    pub valid: usize,
+    pub total: usize,
+}
+
+// What one passport gave for a field.
+#[derive(Clone, Copy, Default)]
+struct Seen<'a> {
+    value: Option<&'a str>,
+    count: usize,
+    conflicted: bool,
+}
+
+impl<'a> Seen<'a> {
+    fn add(&mut self, value: &'a str) {
+        if self.value.is_some_and(|last| last != value) {
+            self.conflicted = true;
+        }
+        self.value = Some(value);
+        self.count += 1;
+    }
+}
+
+// Validates passports straight from their text, for files too big to build a
+// Passport for every record. The ruleset is compiled once and shared by every
+// thread, and fields are checked through slices of the input rather than being
+// copied out. It agrees with Passport::valid on every passport, including
+// those with repeated or unknown fields.
+pub struct PassportValidator {
+    rules: Ruleset,
+}
+
+impl PassportValidator {
+    pub fn new(rules: Ruleset) -> PassportValidator {
+        PassportValidator { rules }
+    }
+
+    // Whether the text of one passport, with its fields separated by any
+    // whitespace, is valid. Malformed tokens are skipped, as PassportReader
+    // does.
+    pub fn valid(&self, record: &str) -> bool {
+        self.valid_with(record, &mut vec![])
+    }
+
+    // Does the work of valid, using fields as scratch space so that it can be
+    // reused from one passport to the next.
+    fn valid_with<'a>(&self, record: &'a str, fields: &mut Vec<Seen<'a>>) -> bool {
+        let rules = &self.rules.fields;
+        let strictness = &self.rules.strictness;
+
+        fields.clear();
+        fields.resize(rules.len(), Seen::default());
+        let mut unknown: Vec<(&str, Seen)> = vec![];
+
+        for token in record.split_whitespace() {
+            let (name, value) = match token.split_once(':') {
+                Some((name, value)) if !name.is_empty() => (name, value),
+                _ => continue,
+            };
+
+            match rules.iter().position(|rule| rule.name == name) {
+                Some(i) => fields[i].add(value),
+                None => match unknown.iter_mut().find(|(unknown, _)| *unknown == name) {
+                    Some((_, seen)) => seen.add(value),
+                    None => {
+                        let mut seen = Seen::default();
+                        seen.add(value);
+                        unknown.push((name, seen));
+                    }
+                },
+            }
+        }
+
+        if strictness.unknown && !unknown.is_empty() {
+            return false;
+        }
+
+        let repeats_allowed = fields
+            .iter()
+            .chain(unknown.iter().map(|(_, seen)| seen))
+            .all(|seen| {
+                if seen.conflicted {
+                    !strictness.conflicts
+                } else {
+                    seen.count <= 1 || !strictness.duplicates
+                }
+            });
+        if !repeats_allowed {
+            return false;
+        }
+
+        rules
+            .iter()
+            .zip(fields.iter())
+            .all(|(rule, seen)| match (seen.value, &rule.validator) {
+                (None, _) => !rule.required,
+                (Some(value), Some(validator)) => validator.validate(value).is_ok(),
+                (Some(_), None) => true,
+            })
+    }
+
+    fn count_batch(&self, batch: &[String]) -> usize {
+        let mut fields = vec![];
+        batch
+            .iter()
+            .filter(|record| self.valid_with(record, &mut fields))
+            .count()
+    }
+
+    // Count the valid passports in the input, reading it on this thread and
+    // validating batches of passports on the given number of threads.
+    pub fn count_valid<R: BufRead>(&self, mut input: R, threads: usize) -> io::Result<Counts> {
+        let (sender, receiver) = mpsc::sync_channel::<Vec<String>>(threads * 2);
+        let receiver = Mutex::new(receiver);
+
+        thread::scope(|scope| {
+            let workers: Vec<_> = (0..threads.max(1))
+                .map(|_| {
+                    scope.spawn(|| {
+                        let mut valid = 0;
+                        loop {
+                            let batch = receiver.lock().expect("a worker panicked").recv();
+                            match batch {
+                                Ok(batch) => valid += self.count_batch(&batch),
+                                Err(_) => return valid,
+                            }
+                        }
+                    })
+                })
+                .collect();
+
+            let mut total = 0;
+            let mut batch = Vec::with_capacity(BATCH_SIZE);
+            let mut record = String::new();
+            let mut line = String::new();
+
+            loop {
+                line.clear();
+                let end = input.read_line(&mut line)? == 0;
+
+                if end || line.trim().is_empty() {
+                    if !record.is_empty() {
+                        batch.push(record.clone());
+                        record.clear();
+                        total += 1;
+                    }
+                    if end || batch.len() == BATCH_SIZE {
+                        let full = std::mem::replace(&mut batch, Vec::with_capacity(BATCH_SIZE));
+                        sender.send(full).expect("the workers stopped early");
+                    }
+                    if end {
+                        break;
+                    }
+                    continue;
+                }
+
+                record.push_str(&line);
+            }
+            drop(sender);
+
+            let valid = workers
+                .into_iter()
+                .map(|worker| worker.join().expect("a worker panicked"))
+                .sum();
+
+            Ok(Counts { valid, total })
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::reader::PassportReader;
+    use crate::rules::Strictness;
+
+    const TRICKY: &str = "byr:1937 iyr:2017 eyr:2020 hgt:183cm hcl:#fffffd ecl:gry pid:860033327
+pid:860033327 oops :1
+
+byr:1937 iyr:2017 eyr:2020 hgt:183cm hcl:#fffffd ecl:gry pid:860033327 zzz:1 zzz:2
+
+byr:1937\tiyr:2017 eyr:2020 hgt:183cm hcl:#fffffd ecl:gry ecl:blu ecl:gry pid:860033327\r
+
+byr:1937 iyr:2017 eyr:2020 hgt:183cm hcl:#fffffd ecl:blu ecl:blu pid:086003332
+";
+
+    fn strictnesses() -> Vec<Strictness> {
+        let mut strictnesses = vec![];
+        for &duplicates in &[false, true] {
+            for &conflicts in &[false, true] {
+                for &unknown in &[false, true] {
+                    strictnesses.push(Strictness {
+                        duplicates,
+                        conflicts,
+                        unknown,
+                    });
+                }
+            }
+        }
+        strictnesses
+    }
+
+    #[test]
+    fn test_agrees_with_passport() {
+        for input in &[include_str!("../input.txt"), TRICKY] {
+            for strictness in strictnesses() {
+                let rules = Ruleset {
+                    strictness,
+                    ..Ruleset::default()
+                };
+
+                let expected: Vec<bool> = PassportReader::new(input.as_bytes())
+                    .map(|record| record.unwrap().passport.valid(&rules))
+                    .collect();
+
+                let validator = PassportValidator::new(rules);
+                let actual: Vec<bool> = input
+                    .split("\n\n")
+                    .map(|record| validator.valid(record))
+                    .collect();
+
+                assert_eq!(actual, expected, "{:?}", strictness);
+            }
+        }
+    }
+
+    #[test]
+    fn test_count_valid() {
+        let validator = PassportValidator::new(Ruleset::default());
+        let input = include_str!("../input.txt");
+
+        for &threads in &[1, 3] {
+            let counts = validator.count_valid(input.as_bytes(), threads).unwrap();
+            assert_eq!(
+                counts,
+                Counts {
+                    valid: 121,
+                    total: 254
+                }
+            );
+        }
+
+        let counts = validator.count_valid(TRICKY.as_bytes(), 2).unwrap();
+        assert_eq!(counts, Counts { valid: 4, total: 4 });
+
+        let validator = PassportValidator::new(Ruleset {
+            strictness: Strictness::strict(),
+            ..Ruleset::default()
+        });
+        let counts = validator.count_valid(TRICKY.as_bytes(), 2).unwrap();
+        assert_eq!(counts, Counts { valid: 0, total: 4 });
+    }
+}
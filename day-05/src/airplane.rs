@@ -1,63 +1,319 @@
-struct SeatScheme {
-    upper_half_char: char,
-    lower_half_char: char,
-    min: usize,
-    max: usize,
+use std::fmt;
+
+use crate::layout::{AircraftLayout, SeatScheme};
+
+#[derive(Debug, PartialEq)]
+pub struct Seat {
+    pub row: usize,
+    pub column: usize,
+    pub id: usize,
+}
+
+// Why a seat code doesn't pick out a seat on the plane. Positions count
+// characters from 1.
+#[derive(Debug, PartialEq)]
+pub enum SeatCodeError {
+    WrongLength {
+        length: usize,
+        expected: usize,
+    },
+    InvalidCharacter {
+        position: usize,
+        character: char,
+        expected: (char, char),
+    },
+    RowOutOfRange {
+        row: usize,
+        rows: usize,
+    },
+    ColumnOutOfRange {
+        column: usize,
+        columns: usize,
+    },
 }
 
-const ROW_SCHEME: SeatScheme = SeatScheme {
-    lower_half_char: 'F',
-    upper_half_char: 'B',
-    min: 0,
-    max: 127,
-};
+impl fmt::Display for SeatCodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SeatCodeError::WrongLength { length, expected } => {
+                write!(f, "is {} characters long instead of {}", length, expected)
+            }
+            SeatCodeError::InvalidCharacter {
+                position,
+                character,
+                expected: (lower, upper),
+            } => write!(
+                f,
+                "has '{}' at position {} where {} or {} should be",
+                character, position, lower, upper
+            ),
+            SeatCodeError::RowOutOfRange { row, rows } => write!(
+                f,
+                "is for row {} but the plane only has rows 0-{}",
+                row,
+                rows - 1
+            ),
+            SeatCodeError::ColumnOutOfRange { column, columns } => write!(
+                f,
+                "is for column {} but the plane only has columns 0-{}",
+                column,
+                columns - 1
+            ),
+        }
+    }
+}
 
-const COLUMN_SCHEME: SeatScheme = SeatScheme {
-    lower_half_char: 'L',
-    upper_half_char: 'R',
-    min: 0,
-    max: 7,
-};
+// Decode a seat code, making sure it's the right length, has only the
+// layout's letters in it and picks out a seat the plane actually has.
+pub fn try_seat_id(seat_code: &str, layout: &AircraftLayout) -> Result<Seat, SeatCodeError> {
+    let letters: Vec<char> = seat_code.chars().collect();
+    let expected = layout.rows.length + layout.columns.length;
+    if letters.len() != expected {
+        return Err(SeatCodeError::WrongLength {
+            length: letters.len(),
+            expected,
+        });
+    }
 
-pub fn seat_id(seat_code: &str) -> usize {
-    let row_code = &seat_code[..7];
-    let column_code = &seat_code[7..];
+    let (row_code, column_code) = letters.split_at(layout.rows.length);
+    let row = find_index(row_code, 0, &layout.rows)?;
+    let column = find_index(column_code, layout.rows.length, &layout.columns)?;
 
-    let row = find_index(row_code, &ROW_SCHEME);
-    let column = find_index(column_code, &COLUMN_SCHEME);
+    if row >= layout.rows.count {
+        return Err(SeatCodeError::RowOutOfRange {
+            row,
+            rows: layout.rows.count,
+        });
+    }
+    if column >= layout.columns.count {
+        return Err(SeatCodeError::ColumnOutOfRange {
+            column,
+            columns: layout.columns.count,
+        });
+    }
 
-    return row * 8 + column;
+    Ok(Seat {
+        row,
+        column,
+        id: layout.seat_id(row, column),
+    })
 }
 
-fn find_index(code: &str, scheme: &SeatScheme) -> usize {
-    let mut min = scheme.min;
-    let mut max = scheme.max;
+// The seat code for a seat id, or None if no seat on the plane has that id.
+pub fn encode(seat_id: usize, layout: &AircraftLayout) -> Option<String> {
+    let (row, column) = layout.seat(seat_id)?;
+    encode_seat(row, column, layout)
+}
 
-    for letter in code.chars() {
-        if min == max {
-            break;
-        }
+// The seat code for a row and column, or None if the plane doesn't have them.
+pub fn encode_seat(row: usize, column: usize, layout: &AircraftLayout) -> Option<String> {
+    if row >= layout.rows.count || column >= layout.columns.count {
+        return None;
+    }
 
-        let offset = (max - min + 1) / 2;
-        if letter == scheme.lower_half_char {
-            max -= offset;
-        } else if letter == scheme.upper_half_char {
-            min += offset;
-        }
+    let mut code = encode_index(row, &layout.rows);
+    code.push_str(&encode_index(column, &layout.columns));
+    Some(code)
+}
+
+// Each letter of the code is a bit of the index, the highest first, where
+// the upper half is a 1.
+fn encode_index(index: usize, scheme: &SeatScheme) -> String {
+    (0..scheme.length)
+        .rev()
+        .map(|bit| {
+            if index & (1 << bit) == 0 {
+                scheme.lower
+            } else {
+                scheme.upper
+            }
+        })
+        .collect()
+}
+
+// Each letter halves the positions left, keeping the lower or upper half,
+// which makes it one bit of the index, the highest first. The offset is where
+// the code starts in the whole seat code, for reporting bad letters.
+fn find_index(code: &[char], offset: usize, scheme: &SeatScheme) -> Result<usize, SeatCodeError> {
+    let mut index = 0;
+
+    for (i, &letter) in code.iter().enumerate() {
+        let bit = if letter == scheme.lower {
+            0
+        } else if letter == scheme.upper {
+            1
+        } else {
+            return Err(SeatCodeError::InvalidCharacter {
+                position: offset + i + 1,
+                character: letter,
+                expected: (scheme.lower, scheme.upper),
+            });
+        };
+        index = index * 2 + bit;
     }
 
-    return min;
+    Ok(index)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn seat_id(seat_code: &str, layout: &AircraftLayout) -> usize {
+        try_seat_id(seat_code, layout).unwrap().id
+    }
+
     #[test]
     fn test_seat_id() {
-        assert_eq!(seat_id("FBFBBFFRLR"), 357);
-        assert_eq!(seat_id("BFFFBBFRRR"), 567);
-        assert_eq!(seat_id("FFFBBBFRRR"), 119);
-        assert_eq!(seat_id("BBFFBBFRLL"), 820);
+        let layout = AircraftLayout::default();
+        assert_eq!(seat_id("FBFBBFFRLR", &layout), 357);
+        assert_eq!(seat_id("BFFFBBFRRR", &layout), 567);
+        assert_eq!(seat_id("FFFBBBFRRR", &layout), 119);
+        assert_eq!(seat_id("BBFFBBFRLL", &layout), 820);
+    }
+
+    #[test]
+    fn test_invalid_codes() {
+        let layout = AircraftLayout::default();
+        assert_eq!(
+            try_seat_id("FBFBBFFRLR", &layout),
+            Ok(Seat {
+                row: 44,
+                column: 5,
+                id: 357
+            })
+        );
+        assert_eq!(
+            try_seat_id("FBF", &layout),
+            Err(SeatCodeError::WrongLength {
+                length: 3,
+                expected: 10
+            })
+        );
+        assert_eq!(
+            try_seat_id("FBFBBFFRLRL", &layout).unwrap_err().to_string(),
+            "is 11 characters long instead of 10"
+        );
+        assert_eq!(
+            try_seat_id("XXXXXXXRRR", &layout).unwrap_err().to_string(),
+            "has 'X' at position 1 where F or B should be"
+        );
+        assert_eq!(
+            try_seat_id("FBFBBFFRLé", &layout).unwrap_err().to_string(),
+            "has 'é' at position 10 where L or R should be"
+        );
+        assert_eq!(
+            try_seat_id("FBFBBFFLRÉÉ", &layout),
+            Err(SeatCodeError::WrongLength {
+                length: 11,
+                expected: 10
+            })
+        );
+
+        let [_, regional, _, _] = layouts();
+        assert_eq!(
+            try_seat_id("BBFFFLLL", &regional).unwrap_err().to_string(),
+            "is for row 24 but the plane only has rows 0-23"
+        );
+        assert_eq!(
+            try_seat_id("FFFFFRRL", &regional).unwrap_err().to_string(),
+            "is for column 6 but the plane only has columns 0-5"
+        );
+    }
+
+    #[test]
+    fn test_encode() {
+        let layout = AircraftLayout::default();
+        assert_eq!(encode(357, &layout), Some("FBFBBFFRLR".to_string()));
+        assert_eq!(encode_seat(102, 4, &layout), Some("BBFFBBFRLL".to_string()));
+        assert_eq!(encode(1024, &layout), None);
+        assert_eq!(encode_seat(0, 8, &layout), None);
+    }
+
+    // Every seat's code decodes back to its id, and ids that aren't seats
+    // don't get a code, whatever the layout.
+    #[test]
+    fn test_encode_round_trip() {
+        for layout in layouts() {
+            let mut seats = 0;
+            let largest = layout.seat_id(layout.rows.count - 1, layout.columns.count - 1);
+            for id in 0..=largest + 10 {
+                if let Some(code) = encode(id, &layout) {
+                    assert_eq!(
+                        code.chars().count(),
+                        layout.rows.length + layout.columns.length
+                    );
+                    assert_eq!(seat_id(&code, &layout), id);
+                    seats += 1;
+                }
+            }
+            assert_eq!(seats, layout.rows.count * layout.columns.count);
+
+            for row in 0..layout.rows.count {
+                for column in 0..layout.columns.count {
+                    let code = encode_seat(row, column, &layout).unwrap();
+                    assert_eq!(seat_id(&code, &layout), layout.seat_id(row, column));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_other_layouts() {
+        let [_, regional, by_column, one_row] = layouts();
+        assert_eq!(seat_id("FFFFFLLL", &regional), 1);
+        assert_eq!(seat_id("BFBBBRLR", &regional), 23 * 6 + 5 + 1);
+        assert_eq!(seat_id("10a", &by_column), 2);
+        assert_eq!(seat_id("11b", &by_column), 7);
+        assert_eq!(encode(3, &one_row), Some("FRR".to_string()));
+        assert_eq!(encode(4, &one_row), None);
+    }
+
+    fn layouts() -> [AircraftLayout; 4] {
+        let regional = AircraftLayout::parse(include_str!("../layouts/regional.toml")).unwrap();
+
+        // Seats numbered down each column, with different letters.
+        let by_column = AircraftLayout::parse(
+            "[rows]
+count = 4
+length = 2
+lower = \"0\"
+upper = \"1\"
+
+[columns]
+count = 2
+length = 1
+lower = \"a\"
+upper = \"b\"
+
+[seat_id]
+row_factor = 1
+column_factor = 4
+",
+        )
+        .unwrap();
+
+        // One row, so the row factor is no bigger than the column factor but
+        // the columns are still the ones inside each row.
+        let one_row = AircraftLayout::parse(
+            "[rows]
+count = 1
+length = 1
+lower = \"F\"
+upper = \"B\"
+
+[columns]
+count = 4
+length = 2
+lower = \"L\"
+upper = \"R\"
+
+[seat_id]
+row_factor = 1
+",
+        )
+        .unwrap();
+
+        [AircraftLayout::default(), regional, by_column, one_row]
     }
 }
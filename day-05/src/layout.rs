@@ -0,0 +1,236 @@
+use std::fmt;
+
+use serde::Deserialize;
+
+// The plane from the puzzle, used unless another layout is given.
+pub const DEFAULT_LAYOUT: &str = include_str!("../layouts/2020.toml");
+
+// How one part of a seat code picks out a row or a column. The code is length
+// letters long, each one keeping either the lower or the upper half of the
+// positions left, and the plane has count of them.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SeatScheme {
+    pub count: usize,
+    pub length: usize,
+    pub lower: char,
+    pub upper: char,
+}
+
+impl SeatScheme {
+    // How many positions the code can pick out, whether or not the plane has
+    // that many.
+    pub fn positions(&self) -> usize {
+        1 << self.length
+    }
+}
+
+// seat id = row * row_factor + column * column_factor + offset
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SeatIdFormula {
+    pub row_factor: usize,
+    #[serde(default = "one")]
+    pub column_factor: usize,
+    #[serde(default)]
+    pub offset: usize,
+}
+
+fn one() -> usize {
+    1
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AircraftLayout {
+    pub rows: SeatScheme,
+    pub columns: SeatScheme,
+    pub seat_id: SeatIdFormula,
+}
+
+#[derive(Debug)]
+pub struct LayoutError(String);
+
+impl fmt::Display for LayoutError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+// Codes longer than this couldn't be decoded into a usize anyway.
+const MAX_CODE_LENGTH: usize = 32;
+
+fn check_scheme(name: &str, scheme: &SeatScheme) -> Result<(), LayoutError> {
+    if scheme.length == 0 || scheme.length > MAX_CODE_LENGTH {
+        return Err(LayoutError(format!(
+            "{} codes must be 1 to {} letters long",
+            name, MAX_CODE_LENGTH
+        )));
+    }
+    if scheme.count == 0 || scheme.count > scheme.positions() {
+        return Err(LayoutError(format!(
+            "a {} letter code can only pick out 1 to {} {}s",
+            scheme.length,
+            scheme.positions(),
+            name
+        )));
+    }
+    if scheme.lower == scheme.upper {
+        return Err(LayoutError(format!(
+            "{} codes need different letters for the lower and upper halves",
+            name
+        )));
+    }
+    Ok(())
+}
+
+impl AircraftLayout {
+    // Read a layout from TOML, checking that every seat code it allows picks
+    // out a seat and that no two seats get the same id.
+    pub fn parse(input: &str) -> Result<AircraftLayout, LayoutError> {
+        let layout: AircraftLayout =
+            toml::from_str(input).map_err(|error| LayoutError(error.to_string()))?;
+
+        check_scheme("row", &layout.rows)?;
+        check_scheme("column", &layout.columns)?;
+
+        let formula = &layout.seat_id;
+        if formula.row_factor == 0 || formula.column_factor == 0 {
+            return Err(LayoutError(
+                "the seat id factors must be at least 1".to_string(),
+            ));
+        }
+
+        // Some other formulas give every seat its own id too, but seat() can
+        // only decode ids where one factor steps over all of the other's seats.
+        let row_span = formula.row_factor.checked_mul(layout.rows.count);
+        let column_span = formula.column_factor.checked_mul(layout.columns.count);
+        let nested = column_span.is_some_and(|span| formula.row_factor >= span)
+            || row_span.is_some_and(|span| formula.column_factor >= span);
+        if !nested {
+            return Err(LayoutError(
+                "row_factor must be at least column_factor * columns, or column_factor at least \
+                 row_factor * rows"
+                    .to_string(),
+            ));
+        }
+
+        let largest = formula
+            .row_factor
+            .checked_mul(layout.rows.count - 1)
+            .and_then(|id| {
+                formula
+                    .column_factor
+                    .checked_mul(layout.columns.count - 1)
+                    .and_then(|column| id.checked_add(column))
+            })
+            .and_then(|id| id.checked_add(formula.offset));
+        if largest.is_none() {
+            return Err(LayoutError("the seat ids are too large".to_string()));
+        }
+
+        Ok(layout)
+    }
+
+    pub fn seat_id(&self, row: usize, column: usize) -> usize {
+        row * self.seat_id.row_factor + column * self.seat_id.column_factor + self.seat_id.offset
+    }
+
+    // The row and column of the seat with the given id, if there's a seat
+    // with that id. parse made sure one factor steps over all the seats for
+    // the other, and that's the one to divide by first.
+    pub fn seat(&self, seat_id: usize) -> Option<(usize, usize)> {
+        let formula = &self.seat_id;
+        let id = seat_id.checked_sub(formula.offset)?;
+
+        let rows_outer = formula
+            .column_factor
+            .checked_mul(self.columns.count)
+            .is_some_and(|span| formula.row_factor >= span);
+        let (row, column, remainder) = if rows_outer {
+            let rest = id % formula.row_factor;
+            (
+                id / formula.row_factor,
+                rest / formula.column_factor,
+                rest % formula.column_factor,
+            )
+        } else {
+            let rest = id % formula.column_factor;
+            (
+                rest / formula.row_factor,
+                id / formula.column_factor,
+                rest % formula.row_factor,
+            )
+        };
+
+        if remainder == 0 && row < self.rows.count && column < self.columns.count {
+            Some((row, column))
+        } else {
+            None
+        }
+    }
+}
+
+impl Default for AircraftLayout {
+    fn default() -> AircraftLayout {
+        AircraftLayout::parse(DEFAULT_LAYOUT).expect("the default layout is valid")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_layout() {
+        let layout = AircraftLayout::default();
+        assert_eq!(layout.rows.positions(), 128);
+        assert_eq!(layout.seat_id(44, 5), 357);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let error = |input: &str| AircraftLayout::parse(input).unwrap_err().to_string();
+        let layout = |rows: &str, seat_id: &str| {
+            format!(
+                "[rows]\n{}\n[columns]\ncount = 8\nlength = 3\nlower = \"L\"\nupper = \"R\"\n[seat_id]\n{}\n",
+                rows, seat_id
+            )
+        };
+        let rows = "count = 128\nlength = 7\nlower = \"F\"\nupper = \"B\"";
+
+        assert!(AircraftLayout::parse(&layout(rows, "row_factor = 8")).is_ok());
+        assert!(
+            AircraftLayout::parse(&layout(rows, "row_factor = 1\ncolumn_factor = 128")).is_ok()
+        );
+        let nesting =
+            "row_factor must be at least column_factor * columns, or column_factor at least \
+             row_factor * rows";
+        assert_eq!(error(&layout(rows, "row_factor = 7")), nesting);
+
+        // Every id here is different, but the factors aren't nested.
+        assert_eq!(
+            error(
+                "[rows]\ncount = 3\nlength = 2\nlower = \"F\"\nupper = \"B\"\n\
+                 [columns]\ncount = 2\nlength = 1\nlower = \"L\"\nupper = \"R\"\n\
+                 [seat_id]\nrow_factor = 3\ncolumn_factor = 2\n"
+            ),
+            nesting
+        );
+        assert_eq!(
+            error(&layout(
+                "count = 129\nlength = 7\nlower = \"F\"\nupper = \"B\"",
+                "row_factor = 8"
+            )),
+            "a 7 letter code can only pick out 1 to 128 rows"
+        );
+        assert_eq!(
+            error(&layout(
+                "count = 128\nlength = 7\nlower = \"F\"\nupper = \"F\"",
+                "row_factor = 8"
+            )),
+            "row codes need different letters for the lower and upper halves"
+        );
+        assert!(AircraftLayout::parse("[rows]\ncount = 1\n").is_err());
+    }
+}
@@ -10,33 +10,155 @@
 // elimination determine which one is yours. The seat id is calculated by the
 // formula seat_id = row_id * 8 + column_id.
 //
-// Usage `cargo run <input-file>`
+// Aircraft layouts:
+//
+// That plane is the default layout, in layouts/2020.toml. Other aircraft can
+// be described in the same format and given with --layout: how many rows and
+// columns they have, the letters for each half, how long the row and column
+// codes are and the formula for the seat id. See layouts/2020.toml for the
+// details and layouts/regional.toml for another plane.
+//
+// Invalid seat codes:
+//
+// Every line is checked before it's decoded: it has to be exactly as long as
+// the layout's codes, use only its letters, and pick out a row and column the
+// plane really has. Lines that don't, or that aren't valid UTF-8 at all, are
+// listed with why, and left out.
+//
+// Reissuing boarding passes:
+//
+// With --encode <seat-id> the seat code for that seat id is printed instead,
+// for whichever layout is in use, so a lost boarding pass can be reissued.
+//
+// Usage `cargo run <input-file> [--layout <layout-file>]`
+//       `cargo run -- --encode <seat-id> [--layout <layout-file>]`
 
-use std::{env, fs::File, io::BufRead, io::BufReader};
+use std::{env, fs, fs::File, io::BufRead, io::BufReader};
 
 mod airplane;
 
+mod layout;
+use crate::layout::AircraftLayout;
+
+const USAGE: &str = concat!(
+    "Usage: cargo run <input-file> [--layout <layout-file>]\n",
+    "       cargo run -- --encode <seat-id> [--layout <layout-file>]"
+);
+
 fn main() {
     let args: Vec<String> = env::args().collect();
 
-    if args.len() < 2 {
-        println!("Usage: cargo run <input-file>");
+    let mut input_file = None;
+    let mut layout_file = None;
+    let mut encode = None;
+
+    let mut rest = args[1..].iter();
+    while let Some(arg) = rest.next() {
+        match arg.as_str() {
+            "--layout" => match rest.next() {
+                Some(file) => layout_file = Some(file),
+                None => {
+                    println!("--layout needs a layout file");
+                    return;
+                }
+            },
+            "--encode" => {
+                match rest
+                    .next()
+                    .and_then(|seat_id| seat_id.parse::<usize>().ok())
+                {
+                    Some(seat_id) => encode = Some(seat_id),
+                    None => {
+                        println!("--encode needs a seat id");
+                        return;
+                    }
+                }
+            }
+            file if input_file.is_none() && !file.starts_with("--") => input_file = Some(file),
+            _ => {
+                println!("{}", USAGE);
+                return;
+            }
+        }
+    }
+
+    let layout = match layout_file {
+        None => AircraftLayout::default(),
+        Some(layout_file) => {
+            let input = fs::read_to_string(layout_file).expect("no such layout file");
+            match AircraftLayout::parse(&input) {
+                Ok(layout) => layout,
+                Err(error) => {
+                    println!("Could not read the layout in {}: {}", layout_file, error);
+                    return;
+                }
+            }
+        }
+    };
+
+    if let Some(seat_id) = encode {
+        match airplane::encode(seat_id, &layout) {
+            Some(code) => println!("Seat {} has the code {}", seat_id, code),
+            None => println!("There's no seat with the id {} on this plane", seat_id),
+        }
         return;
     }
 
-    let input_file = &args[1];
+    let input_file = match input_file {
+        Some(input_file) => input_file,
+        None => {
+            println!("{}", USAGE);
+            return;
+        }
+    };
 
     let file = File::open(input_file).expect("no such file");
     let buf = BufReader::new(file);
-    let seat_codes: Vec<String> = buf
-        .lines()
-        .map(|l| l.expect("could not parse line"))
-        .collect();
-
-    let mut seat_ids: Vec<usize> = seat_codes
-        .iter()
-        .map(|code| airplane::seat_id(code))
-        .collect();
+
+    let mut seat_ids: Vec<usize> = vec![];
+    let mut invalid = 0;
+
+    // Lines are read as bytes so one that isn't UTF-8 is just another invalid
+    // line rather than the end of the run.
+    for (i, line) in buf.split(b'\n').enumerate() {
+        let line = match line {
+            Ok(line) => line,
+            Err(error) => {
+                println!("Could not read {}: {}", input_file, error);
+                return;
+            }
+        };
+        let line = match String::from_utf8(line) {
+            Ok(line) => line,
+            Err(error) => {
+                let line = String::from_utf8_lossy(error.as_bytes()).into_owned();
+                println!("line {}: '{}' is not valid UTF-8", i + 1, line.trim());
+                invalid += 1;
+                continue;
+            }
+        };
+        let code = line.trim();
+        if code.is_empty() {
+            continue;
+        }
+
+        match airplane::try_seat_id(code, &layout) {
+            Ok(seat) => seat_ids.push(seat.id),
+            Err(error) => {
+                println!("line {}: '{}' {}", i + 1, code, error);
+                invalid += 1;
+            }
+        }
+    }
+
+    if invalid > 0 {
+        println!("Skipped {} invalid seat codes", invalid);
+        println!();
+    }
+    if seat_ids.is_empty() {
+        println!("There are no valid seat codes in {}", input_file);
+        return;
+    }
 
     seat_ids.sort();
 